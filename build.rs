@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+const SCHEMA_PATH: &str = "ast.schema";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", SCHEMA_PATH);
+    println!("cargo:rerun-if-changed=tool/generate_ast/src");
+    let schema = std::fs::read_to_string(SCHEMA_PATH).unwrap();
+    let output_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    if let Err(err) = generate_ast::generate(&schema, &output_dir) {
+        panic!("{}: {}", SCHEMA_PATH, err);
+    }
+}
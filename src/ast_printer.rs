@@ -1,16 +1,76 @@
-use super::expr::{Visitor, Expr, Unary, Binary, Literal, Grouping};
+use std::str::FromStr;
+
+use super::error::LoxError;
+use super::expr::{Visitor, Expr, Assign, Unary, Binary, Call, Conditional, Literal, Grouping, Variable};
+use super::rpn_printer::RpnPrinter;
+use super::tree_printer::TreePrinter;
+
+/// Renders an expression tree as text.
+pub trait ExprPrinter {
+    fn print(&mut self, expr: &Expr) -> String;
+}
+
+/// The textual forms an expression tree can be dumped in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AstFormat {
+    /// Lisp-style s-expressions, e.g. `(* (- 1) 2)`.
+    SExpr,
+    /// Reverse Polish Notation, e.g. `1 neg 2 *`.
+    Rpn,
+    /// One node per line, children indented below their parent.
+    Tree,
+}
+
+impl AstFormat {
+    pub fn printer(&self) -> Box<dyn ExprPrinter> {
+        match self {
+            Self::SExpr => Box::new(AstPrinter),
+            Self::Rpn => Box::new(RpnPrinter),
+            Self::Tree => Box::new(TreePrinter::default()),
+        }
+    }
+}
+
+impl FromStr for AstFormat {
+    type Err = LoxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sexpr" => Ok(Self::SExpr),
+            "rpn" => Ok(Self::Rpn),
+            "tree" => Ok(Self::Tree),
+            _ => Err(LoxError::InvalidParameter),
+        }
+    }
+}
 
 pub struct AstPrinter;
 
 impl Visitor<String> for AstPrinter {
     fn visit_unary_expr(&mut self, expr: &Unary) -> String {
         let exprs = [expr.right.as_ref()];
-        self.parenthesize(&expr.operator.lexeme, &exprs)
+        self.parenthesize(expr.operator.lexeme, &exprs)
     }
 
     fn visit_binary_expr(&mut self, expr: &Binary) -> String {
         let exprs = [expr.left.as_ref(), expr.right.as_ref()];
-        self.parenthesize(&expr.operator.lexeme, &exprs)
+        self.parenthesize(expr.operator.lexeme, &exprs)
+    }
+
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        let exprs = [expr.value.as_ref()];
+        self.parenthesize(&format!("= {}", expr.name.lexeme), &exprs)
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> String {
+        let exprs = [expr.condition.as_ref(), expr.then_branch.as_ref(), expr.else_branch.as_ref()];
+        self.parenthesize("?:", &exprs)
     }
 
     fn visit_literal_expr(&mut self, expr: &Literal) -> String {
@@ -21,22 +81,28 @@ impl Visitor<String> for AstPrinter {
         let exprs = [expr.expression.as_ref()];
         self.parenthesize("group", &exprs)
     }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
 }
 
-impl AstPrinter {
-    pub fn print(&mut self, expr: &Expr) -> String {
+impl ExprPrinter for AstPrinter {
+    fn print(&mut self, expr: &Expr) -> String {
         expr.accept(self as &mut dyn Visitor<String>)
     }
+}
 
+impl AstPrinter {
     fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
         let mut result = String::new();
-        result.push_str("(");
+        result.push('(');
         result.push_str(name);
         for expr in exprs {
-            result.push_str(" ");
+            result.push(' ');
             result.push_str(&expr.accept(self as &mut dyn Visitor<String>));
         }
-        result.push_str(")");
-        return result;
+        result.push(')');
+        result
     }
 }
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use super::ast_printer::AstFormat;
+use super::error::LoxError;
+use super::runner::Mode;
+
+pub const USAGE: &str = "\
+Usage: lox [options] [script] [args...]
+
+Options:
+  -e, --eval CODE         Run CODE instead of a script
+      --dump-tokens       Print the tokens of the program instead of running it
+      --dump-ast[=FORMAT] Print the syntax tree instead of running it
+                          (FORMAT is sexpr, rpn or tree, default sexpr)
+      --check             Only check the program for syntax errors
+      --allow-dir DIR     Let the program read and write files in DIR
+                          (may be given more than once)
+      --seed N            Seed random() with N instead of the clock
+  -v, --version           Print the version and exit
+  -h, --help              Print this help and exit
+
+A script of '-' reads the program from standard input, as does running
+without a script when standard input is not a terminal.
+";
+
+/// The command line of the `lox` binary.
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub mode: Mode,
+    /// Source given with `--eval`, run instead of a script.
+    pub eval: Option<String>,
+    pub script: Option<String>,
+    /// Arguments following the script, passed on to the program.
+    pub args: Vec<String>,
+    /// Directories the program may access through the file natives.
+    pub allowed_dirs: Vec<PathBuf>,
+    /// Seed for `random()`, so that a run can be repeated.
+    pub seed: Option<u64>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Options {
+    /// Parses `args`, the first of which is the name of the binary. Options
+    /// end at the first positional argument or at `--`; everything after the
+    /// script belongs to the program.
+    pub fn parse(args: &[String]) -> Result<Options, LoxError> {
+        let mut options = Options::default();
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-e" | "--eval" => {
+                    let code = args.next().ok_or(LoxError::InvalidParameter)?;
+                    options.eval = Some(code.clone());
+                },
+                "--allow-dir" => {
+                    let dir = args.next().ok_or(LoxError::InvalidParameter)?;
+                    options.allowed_dirs.push(PathBuf::from(dir));
+                },
+                "--seed" => {
+                    let seed = args.next().ok_or(LoxError::InvalidParameter)?;
+                    options.seed = Some(seed.parse().map_err(|_| LoxError::InvalidParameter)?);
+                },
+                "--dump-tokens" => options.mode = Mode::DumpTokens,
+                "--dump-ast" => options.mode = Mode::DumpAst(AstFormat::SExpr),
+                "--check" => options.mode = Mode::Check,
+                "-v" | "--version" => options.version = true,
+                "-h" | "--help" => options.help = true,
+                "--" => break,
+                arg if arg.starts_with("--dump-ast=") => {
+                    options.mode = Mode::DumpAst(arg["--dump-ast=".len()..].parse()?);
+                },
+                arg if arg.starts_with('-') && arg != "-" => return Err(LoxError::InvalidParameter),
+                _ => {
+                    options.push_positional(arg);
+                    break;
+                },
+            }
+        }
+        for arg in args {
+            options.push_positional(arg);
+        }
+        Ok(options)
+    }
+
+    // With `--eval` there is no script, so every positional argument is
+    // passed on to the program.
+    fn push_positional(&mut self, arg: &str) {
+        if self.eval.is_none() && self.script.is_none() {
+            self.script = Some(arg.to_string());
+        } else {
+            self.args.push(arg.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, LoxError> {
+        let args: Vec<String> = std::iter::once("lox").chain(args.iter().copied()).map(String::from).collect();
+        Options::parse(&args)
+    }
+
+    #[test]
+    fn parse_options() {
+        let options = parse(&["--dump-ast=rpn", "script.lox", "--check", "a"]).unwrap();
+        assert_eq!(options.mode, Mode::DumpAst(AstFormat::Rpn));
+        assert_eq!(options.script.as_deref(), Some("script.lox"));
+        assert_eq!(options.args, vec!["--check", "a"]);
+
+        let options = parse(&["-e", "print 1;", "a", "b"]).unwrap();
+        assert_eq!(options.eval.as_deref(), Some("print 1;"));
+        assert_eq!(options.script, None);
+        assert_eq!(options.args, vec!["a", "b"]);
+
+        let options = parse(&["--check", "--allow-dir", "data", "--allow-dir", "/tmp", "--seed", "42", "--", "-script.lox"]).unwrap();
+        assert_eq!(options.mode, Mode::Check);
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.allowed_dirs, vec![PathBuf::from("data"), PathBuf::from("/tmp")]);
+        assert_eq!(options.script.as_deref(), Some("-script.lox"));
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        assert!(matches!(parse(&["--bogus"]), Err(LoxError::InvalidParameter)));
+        assert!(matches!(parse(&["--eval"]), Err(LoxError::InvalidParameter)));
+        assert!(matches!(parse(&["--allow-dir"]), Err(LoxError::InvalidParameter)));
+        assert!(matches!(parse(&["--seed", "-1"]), Err(LoxError::InvalidParameter)));
+        assert!(matches!(parse(&["--dump-ast=json"]), Err(LoxError::InvalidParameter)));
+    }
+}
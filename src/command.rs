@@ -0,0 +1,79 @@
+/// The commands understood by the REPL, entered as `:name argument`.
+#[derive(Debug, PartialEq)]
+pub enum Command<'s> {
+    Help,
+    Quit,
+    /// Runs a file in the current session.
+    Load(&'s str),
+    /// Prints the tokens of some source.
+    Tokens(&'s str),
+    /// Prints the syntax tree of some source.
+    Ast(&'s str),
+    /// Lists the global variables and their values.
+    Env,
+    /// Forgets every global defined in the session.
+    Reset,
+    /// Runs some source and prints how long it took.
+    Time(&'s str),
+}
+
+pub const HELP: &str = "\
+:help          Show this help
+:quit          Leave the REPL
+:load FILE     Run FILE in the current session
+:tokens CODE   Print the tokens of CODE
+:ast CODE      Print the syntax tree of CODE
+:env           List the global variables
+:reset         Forget all global variables
+:time CODE     Run CODE and print how long it took
+";
+
+impl<'s> Command<'s> {
+    /// Parses REPL input starting with `:`. Returns `None` for any other
+    /// input, and a message to show for unknown or malformed commands.
+    pub fn parse(input: &'s str) -> Option<Result<Command<'s>, String>> {
+        let input = input.trim().strip_prefix(':')?;
+        let (name, argument) = match input.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (input, ""),
+        };
+        let command = match name {
+            "help" => Command::Help,
+            "quit" => Command::Quit,
+            "env" => Command::Env,
+            "reset" => Command::Reset,
+            "load" | "tokens" | "ast" | "time" if argument.is_empty() => {
+                let usage = if name == "load" { "FILE" } else { "CODE" };
+                return Some(Err(format!("Usage: :{} {}", name, usage)));
+            },
+            "load" => Command::Load(argument),
+            "tokens" => Command::Tokens(argument),
+            "ast" => Command::Ast(argument),
+            "time" => Command::Time(argument),
+            _ => return Some(Err(format!("Unknown command ':{}'. Type :help for a list of commands.", name))),
+        };
+        if !argument.is_empty() && matches!(command, Command::Help | Command::Quit | Command::Env | Command::Reset) {
+            return Some(Err(format!(":{} takes no argument.", name)));
+        }
+        Some(Ok(command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Command::parse("print 1;"), None);
+        assert_eq!(Command::parse(":quit"), Some(Ok(Command::Quit)));
+        assert_eq!(Command::parse(" :ast  1 + 2 "), Some(Ok(Command::Ast("1 + 2"))));
+        assert_eq!(Command::parse(":load\ttest.lox"), Some(Ok(Command::Load("test.lox"))));
+        assert_eq!(Command::parse(":time"), Some(Err(String::from("Usage: :time CODE"))));
+        assert_eq!(Command::parse(":env x"), Some(Err(String::from(":env takes no argument."))));
+        assert_eq!(
+            Command::parse(":bogus"),
+            Some(Err(String::from("Unknown command ':bogus'. Type :help for a list of commands."))),
+        );
+    }
+}
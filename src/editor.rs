@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+    Context, Editor, Helper,
+};
+
+use super::error::LoxError;
+use super::native;
+use super::scanner::KEYWORDS;
+
+/// The outcome of reading a line typed into the REPL.
+#[derive(Debug, PartialEq)]
+pub enum ReadLine {
+    /// A line without its terminator.
+    Line(String),
+    /// Ctrl-C was pressed, abandoning the input typed so far.
+    Interrupted,
+    Eof,
+}
+
+/// Reads the lines typed into the REPL, for prompts that offer more than a
+/// plain `BufRead`.
+pub trait LineEditor {
+    fn read_line(&mut self, prompt: &str) -> Result<ReadLine, LoxError>;
+
+    /// Records a complete piece of input, which may span several lines.
+    fn add_history(&mut self, entry: &str);
+
+    /// The names defined by the program so far, offered for completion.
+    fn set_names(&mut self, names: Vec<String>);
+}
+
+/// A line editor backed by rustyline, with arrow-key editing, reverse search
+/// and a history kept in `~/.lox_history`.
+pub struct RustylineEditor {
+    editor: Editor<LoxHelper, DefaultHistory>,
+    history_path: Option<PathBuf>,
+}
+
+impl RustylineEditor {
+    pub fn new() -> Result<Self, LoxError> {
+        let mut editor = Editor::new().map_err(readline_error)?;
+        editor.set_helper(Some(LoxHelper::default()));
+        let history_path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lox_history"));
+        if let Some(path) = &history_path {
+            // There is no history yet on the first run.
+            let _ = editor.load_history(path);
+        }
+        Ok(Self { editor, history_path })
+    }
+}
+
+impl LineEditor for RustylineEditor {
+    fn read_line(&mut self, prompt: &str) -> Result<ReadLine, LoxError> {
+        match self.editor.readline(prompt) {
+            Ok(line) => Ok(ReadLine::Line(line)),
+            Err(ReadlineError::Interrupted) => Ok(ReadLine::Interrupted),
+            Err(ReadlineError::Eof) => Ok(ReadLine::Eof),
+            Err(err) => Err(readline_error(err)),
+        }
+    }
+
+    fn add_history(&mut self, entry: &str) {
+        if self.editor.add_history_entry(entry).unwrap_or(false) {
+            if let Some(path) = &self.history_path {
+                // Failing to save history is not worth interrupting the session.
+                let _ = self.editor.save_history(path);
+            }
+        }
+    }
+
+    fn set_names(&mut self, names: Vec<String>) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.names = names;
+        }
+    }
+}
+
+fn readline_error(err: ReadlineError) -> LoxError {
+    match err {
+        ReadlineError::Io(err) => LoxError::IOError(err),
+        err => LoxError::IOError(std::io::Error::other(err)),
+    }
+}
+
+#[derive(Default)]
+struct LoxHelper {
+    names: Vec<String>,
+}
+
+impl LoxHelper {
+    // Keywords, natives and defined names starting with `prefix`, sorted.
+    fn candidates(&self, prefix: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = KEYWORDS.keys()
+            .copied()
+            .chain(native::names())
+            .map(String::from)
+            .chain(self.names.iter().cloned())
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_').len();
+        Ok((start, self.candidates(&line[start..pos])))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_keywords_and_names() {
+        let helper = LoxHelper { names: vec![String::from("printer"), String::from("value")] };
+        assert_eq!(helper.candidates("pri"), vec!["print", "printer"]);
+        assert_eq!(helper.candidates("va"), vec!["value", "var"]);
+        assert_eq!(helper.candidates("sq"), vec!["sqrt"]);
+        assert!(helper.candidates("zzz").is_empty());
+    }
+
+    #[test]
+    fn complete_after_multibyte_character() {
+        let helper = LoxHelper { names: vec![String::from("value")] };
+        let history = DefaultHistory::new();
+        let context = Context::new(&history);
+        let line = "print \"é\" + va";
+        assert_eq!(
+            helper.complete(line, line.len(), &context).unwrap(),
+            (line.len() - 2, vec![String::from("value"), String::from("var")]),
+        );
+        assert_eq!(helper.complete("print \"é", 9, &context).unwrap().0, 9);
+    }
+}
@@ -0,0 +1,65 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::error::LoxError;
+use super::token::{Token, Value};
+
+/// Variables and their values, looked up by name. Each block gets its own
+/// environment, enclosed by the one it appears in; the outermost one holds
+/// the globals.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// The variables defined directly in this environment, sorted by name.
+    pub fn entries(&self) -> Vec<(String, Value)> {
+        let mut entries: Vec<(String, Value)> = self.values.iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+        entries
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
+        if let Some(value) = self.values.get(name.lexeme) {
+            return Ok(value.clone());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined(name)),
+        }
+    }
+
+    /// Assigns to an existing variable, in the innermost environment that
+    /// defines it.
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), LoxError> {
+        if let Some(slot) = self.values.get_mut(name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined(name)),
+        }
+    }
+}
+
+fn undefined(name: &Token) -> LoxError {
+    LoxError::RuntimeError {
+        line: name.line as usize,
+        message: format!("Undefined variable '{}'.", name.lexeme),
+    }
+}
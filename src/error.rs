@@ -1,21 +1,113 @@
+use std::fmt::Display;
+
 use thiserror::Error;
 
+use crate::token::{Span, TokenType};
+
 #[derive(Error, Debug)]
 pub enum LoxError {
     #[error("Invalid parameter")]
     InvalidParameter,
-    #[error("[line {line}] Error {where_}: {message}")]
+    #[error("[line {line}] Error{where_}: {message}")]
     ParseError {
         line: usize,
         where_: String,
         message: String,
+        span: Span,
+        expected: Vec<TokenType>,
+        hint: Option<String>,
+    },
+    #[error("{message}\n[line {line}]")]
+    RuntimeError {
+        line: usize,
+        message: String,
+    },
+    #[error("The program has syntax errors")]
+    CompileError,
+    #[error("Interrupted.")]
+    Interrupted,
+    #[error("Can't open '{path}': {source}")]
+    InputError {
+        path: String,
+        source: std::io::Error,
     },
-    #[error("Runtime error")]
-    RuntimeError,
     #[error("IO error: {0}")]
     IOError(#[from] std::io::Error),
 }
 
+impl LoxError {
+    /// The status the `lox` binary exits with for this error, following
+    /// sysexits.h.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::InvalidParameter => 64,
+            Self::ParseError { .. } | Self::CompileError => 65,
+            Self::InputError { .. } => 66,
+            Self::RuntimeError { .. } => 70,
+            Self::IOError(_) => 74,
+            // As for a process killed by SIGINT.
+            Self::Interrupted => 130,
+        }
+    }
+
+    /// Whether the error has already been shown to the user by the time it
+    /// reaches the driver.
+    pub fn is_reported(&self) -> bool {
+        !matches!(self, Self::InputError { .. } | Self::IOError(_))
+    }
+}
+
 pub trait ErrorReporter {
     fn report(&mut self, err: LoxError) -> Result<(), LoxError>;
 }
+
+/// A single problem found while scanning or parsing, detached from the
+/// parser so that it can be handed to callers outside this crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub where_: String,
+    pub message: String,
+    /// Where in the source the problem is.
+    pub span: Span,
+    /// Token types the parser would have accepted at the point of the error.
+    pub expected: Vec<TokenType>,
+    /// A "did you mean" style suggestion for common mistakes.
+    pub hint: Option<String>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] Error{}: {}", self.line, self.where_, self.message)?;
+        match &self.expected[..] {
+            [] => (),
+            [expected] => write!(f, "\n  expected {}", expected)?,
+            expected => {
+                let expected: Vec<String> = expected.iter().map(|token_type| token_type.to_string()).collect();
+                write!(f, "\n  expected one of {}", expected.join(", "))?;
+            },
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<LoxError> for Diagnostic {
+    fn from(err: LoxError) -> Self {
+        match err {
+            LoxError::ParseError { line, where_, message, span, expected, hint } => {
+                Diagnostic { line, where_, message, span, expected, hint }
+            },
+            err => Diagnostic {
+                line: 0,
+                where_: String::new(),
+                message: err.to_string(),
+                span: Span::default(),
+                expected: Vec::new(),
+                hint: None,
+            },
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Generated at build time by tool/generate_ast, see build.rs.
+include!(concat!(env!("OUT_DIR"), "/expr.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast_printer::{AstPrinter, ExprPrinter},
+        stmt::Stmt,
+        token::TokenType,
+    };
+
+    fn parse_expression(source: &str) -> Expr<'_> {
+        match crate::parse(source).unwrap().statements.remove(0) {
+            Stmt::Expression(expression) => expression.expression,
+            _ => panic!("Invalid statement"),
+        }
+    }
+
+    struct ConstantFolder;
+
+    impl<'a> Fold<'a> for ConstantFolder {
+        fn fold_binary_expr(&mut self, binary: Binary<'a>) -> Expr<'a> {
+            let folded = walk_binary_expr_fold(self, binary);
+            if let Expr::Binary(binary) = &folded {
+                if let (Expr::Literal(left), Expr::Literal(right)) = (binary.left.as_ref(), binary.right.as_ref()) {
+                    if let (LoxLiteral::Number(left), LoxLiteral::Number(right)) = (&left.value, &right.value) {
+                        let value = match binary.operator.token_type {
+                            TokenType::Plus => left + right,
+                            TokenType::Star => left * right,
+                            _ => return folded,
+                        };
+                        return Expr::Literal(Literal::new(LoxLiteral::Number(value)));
+                    }
+                }
+            }
+            folded
+        }
+
+        fn fold_grouping_expr(&mut self, grouping: Grouping<'a>) -> Expr<'a> {
+            self.fold_expr(*grouping.expression)
+        }
+    }
+
+    #[test]
+    fn fold_constants() {
+        let expr = ConstantFolder.fold_expr(parse_expression("(1 + 2) * 4 == 2 * (3 - 1);"));
+        assert_eq!(AstPrinter.print(&expr), "(== 12 (* 2 (- 3 1)))");
+    }
+
+    struct Negate;
+
+    impl<'a> VisitorMut<'a> for Negate {
+        fn visit_literal_expr_mut(&mut self, literal: &mut Literal<'a>) {
+            if let LoxLiteral::Number(value) = literal.value {
+                literal.value = LoxLiteral::Number(-value);
+            }
+        }
+    }
+
+    #[test]
+    fn rewrite_in_place() {
+        let mut expr = parse_expression("1 + (2 * 3) ? true : 4;");
+        Negate.visit_expr_mut(&mut expr);
+        assert_eq!(AstPrinter.print(&expr), "(?: (+ -1 (group (* -2 -3))) true -4)");
+    }
+}
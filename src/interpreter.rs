@@ -0,0 +1,402 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    rc::Rc,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use super::environment::Environment;
+use super::error::LoxError;
+use super::expr::{self, Assign, Binary, Call, Conditional, Expr, Grouping, Literal, Unary, Variable};
+use super::native::{self, Random};
+use super::parser::Program;
+use super::sandbox::Sandbox;
+use super::stmt::{self, Block, Expression, Print, Stmt, Var, While};
+use super::token::{Token, TokenType, Value};
+
+/// Walks the syntax tree of a program and evaluates it. Globals persist
+/// between calls to `interpret`, so one interpreter can run a whole REPL
+/// session.
+pub struct Interpreter<'a> {
+    output: Rc<Mutex<&'a mut dyn Write>>,
+    // Read by the `input` and `read_line` natives.
+    input: Option<Rc<Mutex<&'a mut dyn BufRead>>>,
+    globals: Rc<RefCell<Environment>>,
+    // The innermost scope of the code being run.
+    environment: Rc<RefCell<Environment>>,
+    args: Vec<String>,
+    // Set from outside, e.g. on SIGINT, to stop the code being run.
+    interrupt: Arc<AtomicBool>,
+    random: Random,
+    sandbox: Sandbox,
+}
+
+// The natives live in a scope of their own around the globals, so that the
+// program's globals can be listed without them.
+fn new_globals() -> Rc<RefCell<Environment>> {
+    let mut natives = Environment::default();
+    native::define_natives(&mut natives);
+    Rc::new(RefCell::new(Environment::new_enclosed(Rc::new(RefCell::new(natives)))))
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(output: Rc<Mutex<&'a mut dyn Write>>) -> Self {
+        let globals = new_globals();
+        Self {
+            output,
+            input: None,
+            environment: globals.clone(),
+            globals,
+            args: Vec::new(),
+            interrupt: Arc::new(AtomicBool::new(false)),
+            random: Random::default(),
+            sandbox: Sandbox::default(),
+        }
+    }
+
+    /// Forgets every global defined so far.
+    pub fn reset(&mut self) {
+        self.globals = new_globals();
+        self.environment = self.globals.clone();
+    }
+
+    pub fn set_interrupt(&mut self, interrupt: Arc<AtomicBool>) {
+        self.interrupt = interrupt;
+    }
+
+    /// The command-line arguments passed to the program.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    // Poisoning is ignored, as in `Lox`.
+    fn output(&self) -> MutexGuard<'_, &'a mut dyn Write> {
+        self.output.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lets programs read lines from `input`. Without it they see the end of
+    /// input straight away.
+    pub fn set_input(&mut self, input: Rc<Mutex<&'a mut dyn BufRead>>) {
+        self.input = Some(input);
+    }
+
+    /// Reads a line without its terminator, or `None` at the end of input.
+    pub(crate) fn read_line(&mut self) -> io::Result<Option<String>> {
+        let Some(input) = &self.input else {
+            return Ok(None);
+        };
+        let mut line = String::new();
+        if input.lock().unwrap_or_else(PoisonError::into_inner).read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Writes `text` without a newline, e.g. to prompt for input.
+    pub(crate) fn write_prompt(&mut self, text: &str) -> io::Result<()> {
+        let mut output = self.output();
+        output.write_all(text.as_bytes())?;
+        output.flush()
+    }
+
+    /// Restarts the sequence returned by `random()` from `seed`.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    /// The next number in `[0, 1)` from the seeded generator.
+    pub(crate) fn random(&mut self) -> f64 {
+        self.random.next_f64()
+    }
+
+    /// Lets the file natives access `dir` and everything below it.
+    pub fn allow_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.sandbox.allow(dir);
+    }
+
+    pub(crate) fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    /// The global variables defined by the programs run so far, sorted by
+    /// name. Natives are left out.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.globals.borrow().entries()
+    }
+
+    /// Executes the statements of `program` in order, stopping at the first
+    /// runtime error.
+    pub fn interpret(&mut self, program: &Program) -> Result<(), LoxError> {
+        self.interrupt.store(false, Ordering::Relaxed);
+        for statement in &program.statements {
+            self.execute(statement)?;
+        }
+        Ok(())
+    }
+
+    /// Like `interpret`, for a line typed into the REPL. If the last
+    /// statement is an expression its value is returned for the REPL to
+    /// show.
+    pub fn interpret_repl(&mut self, program: &Program) -> Result<Option<Value>, LoxError> {
+        let Some((last, statements)) = program.statements.split_last() else {
+            return Ok(None);
+        };
+        self.interrupt.store(false, Ordering::Relaxed);
+        for statement in statements {
+            self.execute(statement)?;
+        }
+        match last {
+            Stmt::Expression(expression) => Ok(Some(self.evaluate(&expression.expression)?)),
+            _ => self.execute(last).map(|_| None),
+        }
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), LoxError> {
+        if self.interrupt.swap(false, Ordering::Relaxed) {
+            return Err(LoxError::Interrupted);
+        }
+        stmt.accept(self as &mut dyn stmt::Visitor<Result<(), LoxError>>)
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, LoxError> {
+        expr.accept(self as &mut dyn expr::Visitor<Result<Value, LoxError>>)
+    }
+
+    fn execute_block(&mut self, statements: &[Stmt], environment: Environment) -> Result<(), LoxError> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = statements.iter().try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        result
+    }
+}
+
+fn error(token: &Token, message: &str) -> LoxError {
+    LoxError::RuntimeError {
+        line: token.line as usize,
+        message: message.to_string(),
+    }
+}
+
+fn number_operands(operator: &Token, left: &Value, right: &Value) -> Result<(f64, f64), LoxError> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => Ok((*left, *right)),
+        _ => {
+            let message = format!("Operands must be numbers, got {} and {}.", left.type_name(), right.type_name());
+            Err(error(operator, &message))
+        },
+    }
+}
+
+impl<'a> expr::Visitor<Result<Value, LoxError>> for Interpreter<'a> {
+    fn visit_assign_expr(&mut self, expr: &Assign) -> Result<Value, LoxError> {
+        let value = self.evaluate(&expr.value)?;
+        self.environment.borrow_mut().assign(&expr.name, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> Result<Value, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+        let operator = &expr.operator;
+        let value = match operator.token_type {
+            TokenType::Comma => right,
+            TokenType::EqualEqual => Value::Boolean(left.is_equal(&right)),
+            TokenType::BangEqual => Value::Boolean(!left.is_equal(&right)),
+            TokenType::Plus => match (left, right) {
+                (Value::Number(left), Value::Number(right)) => Value::Number(left + right),
+                (Value::String(left), Value::String(right)) => Value::String(left + &right),
+                (left, right) => {
+                    let message = format!(
+                        "Operands must be two numbers or two strings, got {} and {}.",
+                        left.type_name(),
+                        right.type_name(),
+                    );
+                    return Err(error(operator, &message));
+                },
+            },
+            _ => {
+                let (left, right) = number_operands(operator, &left, &right)?;
+                match operator.token_type {
+                    TokenType::Minus => Value::Number(left - right),
+                    TokenType::Star => Value::Number(left * right),
+                    TokenType::Slash => Value::Number(left / right),
+                    TokenType::Greater => Value::Boolean(left > right),
+                    TokenType::GreaterEqual => Value::Boolean(left >= right),
+                    TokenType::Less => Value::Boolean(left < right),
+                    TokenType::LessEqual => Value::Boolean(left <= right),
+                    _ => return Err(error(operator, "Unknown binary operator.")),
+                }
+            },
+        };
+        Ok(value)
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> Result<Value, LoxError> {
+        let callee = self.evaluate(&expr.callee)?;
+        let arguments = expr.arguments.iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<Value>, LoxError>>()?;
+        let callable = match &callee {
+            Value::Object(object) => object.as_callable(),
+            _ => None,
+        }.ok_or_else(|| error(&expr.paren, "Can only call functions and classes."))?;
+        if arguments.len() != callable.arity() {
+            let message = format!("Expected {} arguments but got {}.", callable.arity(), arguments.len());
+            return Err(error(&expr.paren, &message));
+        }
+        callable.call(self, &arguments).map_err(|message| error(&expr.paren, &message))
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> Result<Value, LoxError> {
+        if self.evaluate(&expr.condition)?.is_truthy() {
+            self.evaluate(&expr.then_branch)
+        } else {
+            self.evaluate(&expr.else_branch)
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> Result<Value, LoxError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> Result<Value, LoxError> {
+        Ok(Value::from(&expr.value))
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Unary) -> Result<Value, LoxError> {
+        let right = self.evaluate(&expr.right)?;
+        match (&expr.operator.token_type, right) {
+            (TokenType::Bang, right) => Ok(Value::Boolean(!right.is_truthy())),
+            (TokenType::Minus, Value::Number(right)) => Ok(Value::Number(-right)),
+            (TokenType::Minus, right) => {
+                Err(error(&expr.operator, &format!("Operand must be a number, got {}.", right.type_name())))
+            },
+            _ => Err(error(&expr.operator, "Unknown unary operator.")),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> Result<Value, LoxError> {
+        self.environment.borrow().get(&expr.name)
+    }
+}
+
+impl<'a> stmt::Visitor<Result<(), LoxError>> for Interpreter<'a> {
+    fn visit_block_stmt(&mut self, stmt: &Block) -> Result<(), LoxError> {
+        let environment = Environment::new_enclosed(self.environment.clone());
+        self.execute_block(&stmt.statements, environment)
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Expression) -> Result<(), LoxError> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Print) -> Result<(), LoxError> {
+        let value = self.evaluate(&stmt.expression)?;
+        writeln!(self.output(), "{}", value)?;
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &While) -> Result<(), LoxError> {
+        while self.evaluate(&stmt.condition)?.is_truthy() {
+            self.execute(&stmt.body)?;
+        }
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Var) -> Result<(), LoxError> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment.borrow_mut().define(stmt.name.lexeme, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> (String, Result<(), LoxError>) {
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let program = crate::parse(source).unwrap();
+        let result = Interpreter::new(output).interpret(&program);
+        (String::from_utf8(output_buffer).unwrap(), result)
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        let (output, result) = run("print 1 + 2 * 3; print \"a\" + \"b\"; print !nil; print 1 < 2 ? \"yes\" : \"no\"; print (1, 2);");
+        assert!(result.is_ok());
+        assert_eq!(output, "7\nab\ntrue\nyes\n2\n");
+    }
+
+    #[test]
+    fn scope_variables() {
+        let (output, result) = run("var a = 1; var b; { var a = 2; b = a; print a; } print a; print b = b + 1;");
+        assert!(result.is_ok());
+        assert_eq!(output, "2\n1\n3\n");
+        let (_, result) = run("c = 1;");
+        assert_eq!(result.unwrap_err().to_string(), "Undefined variable 'c'.\n[line 1]");
+    }
+
+    #[test]
+    fn interrupt_loop() {
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let program = crate::parse("var i = 0; while (i < 3) i = i + 1; print i; while (true) {}").unwrap();
+        let mut interpreter = Interpreter::new(output);
+        let interrupt = Arc::new(AtomicBool::new(false));
+        interpreter.set_interrupt(interrupt.clone());
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+        assert!(matches!(interpreter.interpret(&program), Err(LoxError::Interrupted)));
+        handle.join().unwrap();
+        drop(interpreter);
+        assert_eq!(output_buffer, b"3\n");
+    }
+
+    #[test]
+    fn call_natives() {
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let program = crate::parse("print args(); print get(args(), len(args()) - 1); print env(\"LOX_UNSET_VARIABLE\");").unwrap();
+        let mut interpreter = Interpreter::new(output);
+        interpreter.set_args(vec![String::from("a"), String::from("b")]);
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(output_buffer, b"[a, b]\nb\nnil\n");
+
+        let (_, result) = run("print len(1, 2);");
+        assert_eq!(result.unwrap_err().to_string(), "Expected 1 arguments but got 2.\n[line 1]");
+        let (_, result) = run("print \"len\"();");
+        assert_eq!(result.unwrap_err().to_string(), "Can only call functions and classes.\n[line 1]");
+    }
+
+    #[test]
+    fn report_runtime_error() {
+        let (output, result) = run("print 1;\nprint -\"a\";\nprint 2;");
+        assert_eq!(output, "1\n");
+        match result.unwrap_err() {
+            LoxError::RuntimeError { line, message } => {
+                assert_eq!(line, 2);
+                assert_eq!(message, "Operand must be a number, got string.");
+            },
+            err => panic!("Unexpected error {:?}", err),
+        }
+    }
+}
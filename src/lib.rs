@@ -1,8 +1,66 @@
 pub mod lox;
+pub mod cli;
 pub mod runner;
 pub mod error;
 pub mod token;
 pub mod scanner;
 pub mod expr;
+pub mod stmt;
 pub mod ast_printer;
+pub mod rpn_printer;
+pub mod tree_printer;
 pub mod parser;
+pub mod interpreter;
+pub mod environment;
+pub mod native;
+pub mod sandbox;
+pub mod editor;
+pub mod command;
+
+pub use error::Diagnostic;
+pub use parser::Program;
+
+/// Scans and parses `source` into a [`Program`].
+///
+/// All syntax errors found are returned together; nothing is written to
+/// stdout or stderr.
+pub fn parse(source: &str) -> Result<Program<'_>, Vec<Diagnostic>> {
+    parser(source)?.parse()
+}
+
+/// Like [`parse`], for a line typed into the REPL: the last statement may
+/// leave out its terminating `;`.
+pub fn parse_repl(source: &str) -> Result<Program<'_>, Vec<Diagnostic>> {
+    parser(source)?.for_repl().parse()
+}
+
+/// Whether `source` could be a complete program rather than the start of
+/// one: strings are terminated and every `(` and `{` is closed. The REPL
+/// keeps reading lines until its input is complete.
+pub fn is_complete(source: &str) -> bool {
+    let tokens = match scanner::Scanner::new(source).scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(error::LoxError::ParseError { message, .. }) => return message != scanner::UNTERMINATED_STRING,
+        Err(_) => return true,
+    };
+    let mut depth = 0;
+    for token in tokens {
+        match token.token_type {
+            token::TokenType::LeftParen | token::TokenType::LeftBrace => depth += 1,
+            token::TokenType::RightParen | token::TokenType::RightBrace => depth -= 1,
+            _ => (),
+        }
+        // Too many closing brackets won't be fixed by more input.
+        if depth < 0 {
+            return true;
+        }
+    }
+    depth == 0
+}
+
+fn parser(source: &str) -> Result<parser::Parser<'_>, Vec<Diagnostic>> {
+    let tokens = scanner::Scanner::new(source)
+        .scan_tokens()
+        .map_err(|err| vec![Diagnostic::from(err)])?;
+    Ok(parser::Parser::new(tokens))
+}
@@ -1,73 +1,225 @@
 use std::{
-    fs::File,
     io::{
-        Read, Write, BufRead, BufReader
+        Write, BufRead
     },
     rc::Rc,
-    sync::Mutex,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Instant,
 };
 
+use super::ast_printer::AstFormat;
+use super::cli::{Options, USAGE};
+use super::command::{Command, HELP};
+use super::editor::{LineEditor, ReadLine};
 use super::error::LoxError;
-use super::runner::Runner;
+use super::runner::{Mode, Runner};
 
-pub struct Lox<'a, T: Runner> {
+pub struct Lox<'a, T: Runner<'a>> {
     input: Rc<Mutex<&'a mut dyn BufRead>>,
     output: Rc<Mutex<&'a mut dyn Write>>,
     executer: T,
+    // Whether `input` is a terminal. Without a script, input that is not is
+    // read as a whole program instead of line by line.
+    interactive: bool,
+    // Used instead of `input` to read the lines typed into the REPL.
+    editor: Option<Box<dyn LineEditor + 'a>>,
 }
 
-pub fn new <'a, T: Runner> (input: Rc<Mutex<&'a mut dyn BufRead>>, output: Rc<Mutex<&'a mut dyn Write>>, executer: T) -> Lox<'a, T> {
-    Lox { input, output, executer }
+pub fn new <'a, T: Runner<'a>> (input: Rc<Mutex<&'a mut dyn BufRead>>, output: Rc<Mutex<&'a mut dyn Write>>, mut executer: T) -> Lox<'a, T> {
+    executer.set_input(input.clone());
+    Lox { input, output, executer, interactive: true, editor: None }
 }
 
-impl <'a, T: Runner> Lox<'a, T> {
+impl <'a, T: Runner<'a>> Lox<'a, T> {
+    pub fn with_interactive(self, interactive: bool) -> Self {
+        Self { interactive, ..self }
+    }
+
+    pub fn with_editor(self, editor: Box<dyn LineEditor + 'a>) -> Self {
+        Self { editor: Some(editor), ..self }
+    }
+
+    // A panic while the lock was held doesn't leave a reader or writer in a
+    // state worth giving up on, so poisoning is ignored.
+    fn input(&self) -> MutexGuard<'_, &'a mut dyn BufRead> {
+        self.input.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn output(&self) -> MutexGuard<'_, &'a mut dyn Write> {
+        self.output.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     pub fn exec(&mut self, args: Vec<String>) -> Result<(), LoxError> {
-        if args.len() > 2 {
-            self.output.lock().unwrap().write_all(b"Usage: lox [script]\n").unwrap(); // FIXME
-            return Err(LoxError::InvalidParameter);
-        } else if args.len() == 2 {
-            self.run_file(args[1].clone()).unwrap();
+        let options = match Options::parse(&args) {
+            Ok(options) => options,
+            Err(err) => {
+                self.output().write_all(USAGE.as_bytes())?;
+                return Err(err);
+            },
+        };
+        if options.help {
+            self.output().write_all(USAGE.as_bytes())?;
+            return Ok(());
+        }
+        if options.version {
+            writeln!(self.output(), "lox {}", env!("CARGO_PKG_VERSION"))?;
+            return Ok(());
+        }
+        self.executer.configure(&options);
+        if let Some(code) = options.eval {
+            self.run(code, false)
+        } else if options.script.as_deref() == Some("-") || (options.script.is_none() && !self.interactive) {
+            self.run_input()
+        } else if let Some(script) = options.script {
+            self.run_file(script)
         } else {
-            self.run_prompt()?;
+            self.run_prompt()
         }
-        Ok(())
     }
 
-    fn run_file(&mut self, path: String) -> Result<(), LoxError> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
+    fn run_input(&mut self) -> Result<(), LoxError> {
         let mut contents = String::new();
-        reader.read_to_string(&mut contents)?;
+        self.input().read_to_string(&mut contents)?;
+        self.run(contents, false)
+    }
+
+    fn run_file(&mut self, path: String) -> Result<(), LoxError> {
+        // A directory or a file that isn't UTF-8 is as unusable as a missing
+        // one.
+        let contents = std::fs::read_to_string(&path).map_err(|source| LoxError::InputError { path, source })?;
         self.run(contents, false)?;
         Ok(())
     }
 
     fn run_prompt(&mut self) -> Result<(), LoxError> {
-        loop {
-            self.output.lock().unwrap().write_all(b"> ")?;
-            self.output.lock().unwrap().flush()?;
-            let mut line = String::new();
-            self.input.lock().unwrap().read_line(&mut line)?;
-            line = line.trim_end().to_string();
-            if line == String::from("exit") {
+        // Without `exit` or `:quit`, the session ends at the end of input.
+        while let Some(source) = self.read_input()? {
+            if source == "exit" {
                 break;
             }
-            self.run(line, true)?;
+            match Command::parse(&source) {
+                Some(Ok(Command::Quit)) => break,
+                Some(Ok(command)) => self.run_command(command)?,
+                Some(Err(message)) => writeln!(self.output(), "{}", message)?,
+                None => self.run(source, true)?,
+            }
         }
         Ok(())
     }
 
-    fn run(&mut self, source: String, ignore_interpreter_error: bool) -> Result<(), LoxError> {
-        if let Err(err) = self.executer.run(source) {
+    fn run_command(&mut self, command: Command) -> Result<(), LoxError> {
+        match command {
+            Command::Help => self.output().write_all(HELP.as_bytes())?,
+            Command::Quit => (),
+            // A loaded file is parsed like a script, but its errors don't end
+            // the session.
+            Command::Load(path) => match std::fs::read_to_string(path) {
+                Ok(contents) => {
+                    let result = self.executer.run(contents);
+                    self.report(result, true)?;
+                },
+                Err(err) => writeln!(self.output(), "Can't read '{}': {}", path, err)?,
+            },
+            Command::Tokens(source) => self.executer.run_with_mode(source.to_string(), Mode::DumpTokens)?,
+            Command::Ast(source) => self.executer.run_with_mode(source.to_string(), Mode::DumpAst(AstFormat::SExpr))?,
+            Command::Env => {
+                let globals = self.executer.globals();
+                let mut output = self.output();
+                for (name, value) in globals {
+                    writeln!(output, "{} = {}", name, value)?;
+                }
+            },
+            Command::Reset => self.executer.reset(),
+            Command::Time(source) => {
+                let start = Instant::now();
+                self.run(source.to_string(), true)?;
+                writeln!(self.output(), "Took {:.3?}", start.elapsed())?;
+            },
+        }
+        Ok(())
+    }
+
+    // Reads lines until they form a complete program, prompting with `... `
+    // for each line after the first. Ctrl-C throws away the lines read so far
+    // and starts over. Returns `None` at the end of input.
+    fn read_input(&mut self) -> Result<Option<String>, LoxError> {
+        if let Some(editor) = &mut self.editor {
+            editor.set_names(self.executer.globals().into_iter().map(|(name, _)| name).collect());
+        }
+        let mut source = String::new();
+        let mut prompt = "> ";
+        loop {
+            match self.read_line(prompt)? {
+                ReadLine::Line(line) => {
+                    source.push_str(&line);
+                    source.push('\n');
+                },
+                ReadLine::Interrupted => {
+                    source.clear();
+                    prompt = "> ";
+                    continue;
+                },
+                ReadLine::Eof if source.is_empty() => {
+                    // Leave the shell's prompt on a line of its own.
+                    writeln!(self.output())?;
+                    return Ok(None);
+                },
+                ReadLine::Eof => break,
+            }
+            if crate::is_complete(&source) {
+                break;
+            }
+            prompt = "... ";
+        }
+        let source = source.trim_end().to_string();
+        if let Some(editor) = &mut self.editor {
+            if !source.is_empty() {
+                editor.add_history(&source);
+            }
+        }
+        Ok(Some(source))
+    }
+
+    fn read_line(&mut self, prompt: &str) -> Result<ReadLine, LoxError> {
+        if let Some(editor) = &mut self.editor {
+            return editor.read_line(prompt);
+        }
+        self.output().write_all(prompt.as_bytes())?;
+        self.output().flush()?;
+        let mut line = String::new();
+        if self.input().read_line(&mut line)? == 0 {
+            return Ok(ReadLine::Eof);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        Ok(ReadLine::Line(line))
+    }
+
+    fn run(&mut self, source: String, repl: bool) -> Result<(), LoxError> {
+        let result = if repl {
+            self.executer.run_repl(source)
+        } else {
+            self.executer.run(source)
+        };
+        self.report(result, repl)
+    }
+
+    // In the REPL runtime errors and interruptions are reported and the
+    // session goes on.
+    fn report(&mut self, result: Result<(), LoxError>, repl: bool) -> Result<(), LoxError> {
+        if let Err(err) = result {
             match err {
-                LoxError::RuntimeError => {
-                    self.output.lock().unwrap().write_all(format!("{}", err).as_bytes())?;
-                    if !ignore_interpreter_error {
+                LoxError::RuntimeError { .. } | LoxError::Interrupted => {
+                    writeln!(self.output(), "{}", err)?;
+                    if !repl {
                         return Err(err)
                     } else {
                         return Ok(())
                     }
                 },
+                // Already reported by the runner.
+                LoxError::CompileError if repl => return Ok(()),
                 _ => return Err(err),
             }
         }
@@ -78,10 +230,11 @@ impl <'a, T: Runner> Lox<'a, T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runner::LoxRunner;
 
     struct MockRunner<'a>(Rc<Mutex<&'a mut dyn Write>>);
 
-    impl<'a> Runner for MockRunner<'a> {
+    impl<'a> Runner<'a> for MockRunner<'a> {
         fn run(&mut self, source: String) -> Result<(), LoxError> {
             self.0.lock().unwrap().write_all(source.as_bytes())?;
             Ok(())
@@ -94,11 +247,70 @@ mod tests {
         let mut output_buffer = Vec::new();
         let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
         let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
-        match interpreter.exec(vec![String::from("a"), String::from("b"), String::from("c")]).unwrap_err() {
+        match interpreter.exec(vec![String::from("lox"), String::from("--bogus")]).unwrap_err() {
             LoxError::InvalidParameter => (),
             _ => panic!("Invalid error"),
         }
-        assert_eq!(output_buffer, b"Usage: lox [script]\n");
+        drop(interpreter);
+        assert_eq!(output_buffer, USAGE.as_bytes());
+    }
+
+    #[test]
+    fn eval() {
+        let mut input = "".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
+        let args = vec![String::from("lox"), String::from("-e"), String::from("hello"), String::from("world")];
+        let err = interpreter.exec(args).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"hello");
+    }
+
+    fn exec_with_runner(args: &[&str]) -> (String, Result<(), LoxError>) {
+        let mut input = "".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), LoxRunner::new(output.clone()));
+        let result = interpreter.exec(args.iter().map(|arg| arg.to_string()).collect());
+        drop(interpreter);
+        drop(output);
+        (String::from_utf8(output_buffer).unwrap(), result)
+    }
+
+    #[test]
+    fn seed_random() {
+        let args = ["lox", "--seed", "7", "-e", "print random(); print random();"];
+        let (first, _) = exec_with_runner(&args);
+        let (second, _) = exec_with_runner(&args);
+        assert_eq!(first, second);
+        let (other, _) = exec_with_runner(&["lox", "--seed", "8", "-e", "print random(); print random();"]);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn exit_codes() {
+        let (output, result) = exec_with_runner(&["lox", "-e", "print 1 +;"]);
+        assert_eq!(output, "[line 1] Error at ';': Expect expression.\n  expected one of '!', '-', 'false', 'true', 'nil', number, string, identifier, '('\n");
+        assert_eq!(result.unwrap_err().exit_code(), 65);
+        let (output, result) = exec_with_runner(&["lox", "-e", "print 1; print -nil;"]);
+        assert_eq!(output, "1\nOperand must be a number, got nil.\n[line 1]\n");
+        assert_eq!(result.unwrap_err().exit_code(), 70);
+        let (output, result) = exec_with_runner(&["lox", "/nonexistent/script.lox"]);
+        assert_eq!(output, "");
+        let err = result.unwrap_err();
+        assert!(!err.is_reported());
+        assert_eq!(err.exit_code(), 66);
+        let dir = tempfile::tempdir().unwrap();
+        let (_, result) = exec_with_runner(&["lox", dir.path().to_str().unwrap()]);
+        assert_eq!(result.unwrap_err().exit_code(), 66);
+        let script = dir.path().join("latin1.lox");
+        std::fs::write(&script, b"print \"caf\xe9\";").unwrap();
+        let (_, result) = exec_with_runner(&["lox", script.to_str().unwrap()]);
+        assert_eq!(result.unwrap_err().exit_code(), 66);
+        let (_, result) = exec_with_runner(&["lox", "--check", "-e", "print 1;"]);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -111,10 +323,174 @@ mod tests {
         let file_path = tmpfile.path().to_str().unwrap().to_string();
         let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
         let err = interpreter.exec(vec![String::from("lox"), file_path]).err();
-        assert_eq!(err.is_none(), true);
+        assert!(err.is_none());
+        drop(interpreter);
         assert_eq!(output_buffer, b"hello\nworld");
     }
 
+    #[test]
+    fn run_input() {
+        let mut input = "hello\nworld\nexit".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
+        let err = interpreter.exec(vec![String::from("lox"), String::from("-")]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"hello\nworld\nexit");
+
+        let mut input = "hello\nworld".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()))
+            .with_interactive(false);
+        let err = interpreter.exec(vec![String::from("lox")]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"hello\nworld");
+    }
+
+    #[test]
+    fn run_prompt_keeps_state() {
+        let mut input = "var x = 1;\nx = x + 1;\nprint x;\nx * 10\nexit".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let runner = LoxRunner::new(output.clone());
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), runner);
+        let err = interpreter.exec(vec![]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"> > 2\n> 2\n> 20\n> ");
+    }
+
+    #[test]
+    fn read_program_input() {
+        let mut input = "Ada\r\nprint read_line();\nexit\n".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), LoxRunner::new(output.clone()));
+        let source = "print \"Hello, \" + input(\"Name? \") + \"!\";";
+        assert!(interpreter.exec(vec![String::from("lox"), String::from("-e"), String::from(source)]).is_ok());
+        // The REPL and the program take turns reading the same input.
+        assert!(interpreter.exec(vec![]).is_ok());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"Name? Hello, Ada!\n> exit\n> \n");
+    }
+
+    #[test]
+    fn run_prompt_multiline() {
+        let mut input = "{\n  (1 +\n2);\n}\n\"a\nb\"\nexit".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
+        let err = interpreter.exec(vec![]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"> ... ... ... {\n  (1 +\n2);\n}> ... \"a\nb\"> ");
+    }
+
+    struct MockEditor<'a> {
+        // Popped from the end; `None` stands for Ctrl-C.
+        lines: Vec<Option<&'static str>>,
+        history: &'a mut Vec<String>,
+    }
+
+    impl<'a> LineEditor for MockEditor<'a> {
+        fn read_line(&mut self, _prompt: &str) -> Result<ReadLine, LoxError> {
+            Ok(match self.lines.pop() {
+                Some(Some(line)) => ReadLine::Line(line.to_string()),
+                Some(None) => ReadLine::Interrupted,
+                None => ReadLine::Eof,
+            })
+        }
+
+        fn add_history(&mut self, entry: &str) {
+            self.history.push(entry.to_string());
+        }
+
+        fn set_names(&mut self, _names: Vec<String>) {}
+    }
+
+    #[test]
+    fn run_prompt_with_editor() {
+        let mut input = "".as_bytes();
+        let mut output_buffer = Vec::new();
+        let mut history = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let editor = MockEditor { lines: vec![Some("exit"), Some("2)"), Some("(1,"), Some("hello")], history: &mut history };
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()))
+            .with_editor(Box::new(editor));
+        let err = interpreter.exec(vec![]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"hello(1,\n2)");
+        assert_eq!(history, vec!["hello", "(1,\n2)", "exit"]);
+    }
+
+    #[test]
+    fn interrupt_continued_input() {
+        let mut input = "".as_bytes();
+        let mut output_buffer = Vec::new();
+        let mut history = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let editor = MockEditor { lines: vec![Some("print 1;"), None, Some("{")], history: &mut history };
+        let runner = LoxRunner::new(output.clone());
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), runner)
+            .with_editor(Box::new(editor));
+        assert!(interpreter.exec(vec![]).is_ok());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"1\n\n");
+        assert_eq!(history, vec!["print 1;"]);
+    }
+
+    #[test]
+    fn run_prompt_commands() {
+        let mut input = "var a = 1;\n:env\n:ast a + 2\n:reset\n:env\n:bogus\n:quit\nprint 2;".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let runner = LoxRunner::new(output.clone());
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), runner);
+        let err = interpreter.exec(vec![]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        let output = String::from_utf8(output_buffer).unwrap();
+        // Natives are not listed, so nothing is left after the reset.
+        assert!(output.starts_with("> > a = 1\n> (+ a 2)\n> > > Unknown"));
+        assert!(output.ends_with("> Unknown command ':bogus'. Type :help for a list of commands.\n> "));
+    }
+
+    #[test]
+    fn run_prompt_load() {
+        let mut script = tempfile::NamedTempFile::new().unwrap();
+        write!(script, "var a = 1;\na + 1;\nprint a").unwrap();
+        let source = format!(":load {0}\nprint 2;\n:load {0}", script.path().display());
+        let mut input = source.as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let runner = LoxRunner::new(output.clone());
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), runner);
+        assert!(interpreter.exec(vec![]).is_ok());
+        drop(interpreter);
+        // Loaded files are parsed as scripts: the final ';' is required and
+        // expressions are not printed.
+        let output = String::from_utf8(output_buffer).unwrap();
+        assert!(output.starts_with("> [line 3] Error at end: Expect ';' after value."));
+        assert!(output.contains("?\n> 2\n> [line 3] Error"));
+        assert!(output.ends_with("?\n> \n"));
+    }
+
+    #[test]
+    fn run_prompt_until_eof() {
+        let mut input = "hello\n(world".as_bytes();
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
+        let err = interpreter.exec(vec![]).err();
+        assert!(err.is_none());
+        drop(interpreter);
+        assert_eq!(output_buffer, b"> hello> ... (world> \n");
+    }
+
     #[test]
     fn run_prompt() {
         let mut input = "hello\nworld\nexit".as_bytes();
@@ -122,7 +498,8 @@ mod tests {
         let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
         let mut interpreter = new(Rc::new(Mutex::new(&mut input)), output.clone(), MockRunner(output.clone()));
         let err = interpreter.exec(vec![]).err();
-        assert_eq!(err.is_none(), true);
+        assert!(err.is_none());
+        drop(interpreter);
         assert_eq!(output_buffer, b"> hello> world> ");
     }
 }
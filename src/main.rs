@@ -1,55 +1,44 @@
-use rlox::{
-    ast_printer::AstPrinter,
-    error, runner,
-    expr::{Binary, Expr, Grouping, Literal, Unary},
-    lox,
-    token::{Literal as LiteralToken, Token, TokenType},
-};
+use rlox::{editor::RustylineEditor, runner, lox};
 use std::{
-    io::{BufRead, Write},
+    io::{BufRead, IsTerminal, Write},
     rc::Rc,
-    sync::Mutex,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
 };
 
 fn main() {
+    let interactive = std::io::stdin().is_terminal();
     let mut stdin = std::io::stdin().lock();
     let mut stdout = std::io::stdout().lock();
     let input: Rc<Mutex<&mut dyn BufRead>> = Rc::new(Mutex::new(&mut stdin));
     let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut stdout));
-    let expression: Expr = Expr::Binary(Binary::new(
-        Expr::Unary(Unary::new(
-            Token::new(TokenType::Minus, "-", LiteralToken::Nil, 1),
-            Expr::Literal(Literal::new(LiteralToken::Number(123f64))),
-        )),
-        Token::new(TokenType::Star, "*", LiteralToken::Nil, 1),
-        Expr::Grouping(Grouping::new(Expr::Literal(Literal::new(
-            LiteralToken::Number(45.67f64),
-        )))),
-    ));
-    let mut printer: AstPrinter = AstPrinter;
-    let result = printer.print(&expression) + "\n";
-    std::io::stdout()
-        .lock()
-        .write_all(result.as_bytes())
-        .unwrap();
-    if let Err(err) = lox::new(
-        input,
-        output.clone(),
-        runner::LoxRunner::new(output.clone()),
-    )
-    .exec(std::env::args().collect())
-    {
-        match err {
-            error::LoxError::InvalidParameter => {
-                std::process::exit(64);
-            }
-            error::LoxError::RuntimeError => {
-                std::process::exit(65);
-            }
-            _ => {
-                eprintln!("{}", err);
-                std::process::exit(1);
-            }
+    // Ctrl-C stops the code being run instead of the whole process.
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let handler_interrupt = interrupt.clone();
+    let _ = ctrlc::set_handler(move || {
+        // The flag is only checked between statements, so a second Ctrl-C
+        // before it was noticed, e.g. while a program waits for input, ends
+        // the process as usual.
+        if handler_interrupt.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    });
+    let runner = runner::LoxRunner::new(output.clone()).with_interrupt(interrupt);
+    let mut lox = lox::new(input, output.clone(), runner).with_interactive(interactive);
+    if interactive {
+        // Without a usable terminal the prompt falls back to plain input.
+        if let Ok(editor) = RustylineEditor::new() {
+            lox = lox.with_editor(Box::new(editor));
+        }
+    }
+    let result = lox.exec(std::env::args().collect());
+    // `process::exit` skips destructors, so flush what the program printed.
+    drop(lox);
+    drop(output);
+    let _ = stdout.flush();
+    if let Err(err) = result {
+        if !err.is_reported() {
+            eprintln!("lox: {}", err);
         }
+        std::process::exit(err.exit_code());
     }
 }
@@ -0,0 +1,557 @@
+use std::{fs, io::Write, path::PathBuf, rc::Rc};
+
+use super::environment::Environment;
+use super::interpreter::Interpreter;
+use super::token::{format_number, Object, Value};
+
+/// Something a Lox program can call.
+pub trait Callable {
+    fn arity(&self) -> usize;
+
+    /// Runs the callable. An error is reported as a runtime error at the call.
+    fn call(&self, interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String>;
+}
+
+type NativeFn = fn(&mut Interpreter, &[Value]) -> Result<Value, String>;
+
+/// A function implemented in Rust and exposed to Lox programs as a global.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    arity: usize,
+    function: NativeFn,
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl std::fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Object for NativeFunction {
+    fn type_name(&self) -> &'static str {
+        "function"
+    }
+
+    fn as_callable(&self) -> Option<&dyn Callable> {
+        Some(self)
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+const NATIVES: &[(&str, usize, NativeFn)] = &[
+    ("args", 0, args),
+    ("env", 1, env),
+    ("len", 1, len),
+    ("get", 2, get),
+    ("substr", 3, substr),
+    ("index_of", 2, index_of),
+    ("split", 2, split),
+    ("join", 2, join),
+    ("upper", 1, upper),
+    ("lower", 1, lower),
+    ("trim", 1, trim),
+    ("replace", 3, replace),
+    ("starts_with", 2, starts_with),
+    ("chars", 1, chars),
+    ("sqrt", 1, sqrt),
+    ("pow", 2, pow),
+    ("floor", 1, floor),
+    ("ceil", 1, ceil),
+    ("round", 1, round),
+    ("abs", 1, abs),
+    ("min", 2, min),
+    ("max", 2, max),
+    ("sin", 1, sin),
+    ("cos", 1, cos),
+    ("tan", 1, tan),
+    ("asin", 1, asin),
+    ("acos", 1, acos),
+    ("atan", 1, atan),
+    ("atan2", 2, atan2),
+    ("random", 0, random),
+    ("read_file", 1, read_file),
+    ("write_file", 2, write_file),
+    ("append_file", 2, append_file),
+    ("read_lines", 1, read_lines),
+    ("file_exists", 1, file_exists),
+    ("list_dir", 1, list_dir),
+    ("input", 1, input),
+    ("read_line", 0, read_line),
+];
+
+const CONSTANTS: &[(&str, f64)] = &[
+    ("PI", std::f64::consts::PI),
+    ("E", std::f64::consts::E),
+];
+
+/// The names of the native functions and constants.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    NATIVES.iter().map(|&(name, _, _)| name).chain(CONSTANTS.iter().map(|&(name, _)| name))
+}
+
+/// Defines every native function and constant in `environment`.
+pub fn define_natives(environment: &mut Environment) {
+    for &(name, arity, function) in NATIVES {
+        environment.define(name, Value::Object(Rc::new(NativeFunction { name, arity, function })));
+    }
+    for &(name, value) in CONSTANTS {
+        environment.define(name, Value::Number(value));
+    }
+}
+
+/// A small deterministic generator (SplitMix64), so that a program run
+/// with the same seed sees the same numbers.
+#[derive(Debug, Default)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The next number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // The top 53 bits fill the mantissa exactly.
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn argument_error(function: &str, index: usize, expected: &str, value: &Value) -> String {
+    format!("Argument {} to '{}' must be {}, got {}.", index + 1, function, expected, value.type_name())
+}
+
+fn string<'v>(function: &str, arguments: &'v [Value], index: usize) -> Result<&'v str, String> {
+    match &arguments[index] {
+        Value::String(string) => Ok(string),
+        value => Err(argument_error(function, index, "a string", value)),
+    }
+}
+
+fn number(function: &str, arguments: &[Value], index: usize) -> Result<f64, String> {
+    match &arguments[index] {
+        Value::Number(number) => Ok(*number),
+        value => Err(argument_error(function, index, "a number", value)),
+    }
+}
+
+fn list<'v>(function: &str, arguments: &'v [Value], index: usize) -> Result<&'v [Value], String> {
+    match &arguments[index] {
+        Value::List(values) => Ok(values),
+        value => Err(argument_error(function, index, "a list", value)),
+    }
+}
+
+// A position in a string or list, which must be a whole number. Whether it
+// is in bounds is up to the caller, which must check before casting it to
+// `usize` so that a huge position is not silently saturated.
+fn position(function: &str, arguments: &[Value], index: usize) -> Result<f64, String> {
+    match &arguments[index] {
+        Value::Number(number) if number.fract() == 0.0 && *number >= 0.0 => Ok(*number),
+        // A number has the right type, so show which one it was.
+        Value::Number(number) => Err(format!(
+            "Argument {} to '{}' must be a non-negative integer, got {}.",
+            index + 1,
+            function,
+            format_number(*number),
+        )),
+        value => Err(argument_error(function, index, "a non-negative integer", value)),
+    }
+}
+
+/// The arguments following the script on the command line, as a list of
+/// strings.
+fn args(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::List(interpreter.args().iter().cloned().map(Value::String).collect()))
+}
+
+/// The value of an environment variable, or nil if it is not set.
+fn env(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let name = string("env", arguments, 0)?;
+    Ok(std::env::var(name).map(Value::String).unwrap_or(Value::Nil))
+}
+
+/// The number of elements in a list, or of characters in a string. Like the
+/// other string natives this counts Unicode scalar values, so a character
+/// made of several code points, such as an accented letter written with a
+/// combining mark, counts as more than one.
+fn len(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    match &arguments[0] {
+        Value::List(values) => Ok(Value::Number(values.len() as f64)),
+        Value::String(string) => Ok(Value::Number(string.chars().count() as f64)),
+        value => Err(argument_error("len", 0, "a list or a string", value)),
+    }
+}
+
+/// The element of a list at a zero-based index.
+fn get(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let values = list("get", arguments, 0)?;
+    let index = position("get", arguments, 1)?;
+    if index >= values.len() as f64 {
+        return Err(format!("Index {} is out of bounds for a list of length {}.", format_number(index), values.len()));
+    }
+    Ok(values[index as usize].clone())
+}
+
+/// The characters of a string from `start` up to but not including `end`,
+/// counted in Unicode scalar values as by `len`.
+fn substr(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string("substr", arguments, 0)?;
+    let start = position("substr", arguments, 1)?;
+    let end = position("substr", arguments, 2)?;
+    let length = string.chars().count();
+    if start > end || end > length as f64 {
+        return Err(format!(
+            "Range {}..{} is out of bounds for a string of length {}.",
+            format_number(start),
+            format_number(end),
+            length,
+        ));
+    }
+    let (start, end) = (start as usize, end as usize);
+    Ok(Value::String(string.chars().skip(start).take(end - start).collect()))
+}
+
+/// The position in characters of the first occurrence of a substring, or -1.
+fn index_of(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string("index_of", arguments, 0)?;
+    let needle = self::string("index_of", arguments, 1)?;
+    let index = string.find(needle).map_or(-1.0, |byte| string[..byte].chars().count() as f64);
+    Ok(Value::Number(index))
+}
+
+fn split(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string("split", arguments, 0)?;
+    let separator = self::string("split", arguments, 1)?;
+    if separator.is_empty() {
+        return Err(String::from("Separator for 'split' must not be empty."));
+    }
+    Ok(Value::List(string.split(separator).map(|part| Value::String(part.to_string())).collect()))
+}
+
+/// The elements of a list as they would be printed, separated by a string.
+fn join(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let values = list("join", arguments, 0)?;
+    let separator = string("join", arguments, 1)?;
+    let parts: Vec<String> = values.iter().map(Value::to_string).collect();
+    Ok(Value::String(parts.join(separator)))
+}
+
+fn upper(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::String(string("upper", arguments, 0)?.to_uppercase()))
+}
+
+fn lower(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::String(string("lower", arguments, 0)?.to_lowercase()))
+}
+
+/// A string without leading and trailing whitespace.
+fn trim(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::String(string("trim", arguments, 0)?.trim().to_string()))
+}
+
+/// A string with every occurrence of one substring replaced by another.
+fn replace(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string("replace", arguments, 0)?;
+    let from = self::string("replace", arguments, 1)?;
+    let to = self::string("replace", arguments, 2)?;
+    if from.is_empty() {
+        return Err(String::from("String to replace must not be empty."));
+    }
+    Ok(Value::String(string.replace(from, to)))
+}
+
+fn starts_with(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string("starts_with", arguments, 0)?;
+    let prefix = self::string("starts_with", arguments, 1)?;
+    Ok(Value::Boolean(string.starts_with(prefix)))
+}
+
+/// The characters of a string, as a list of one-character strings.
+fn chars(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let string = string("chars", arguments, 0)?;
+    Ok(Value::List(string.chars().map(|c| Value::String(c.to_string())).collect()))
+}
+
+fn unary(function: &str, arguments: &[Value], operation: fn(f64) -> f64) -> Result<Value, String> {
+    Ok(Value::Number(operation(number(function, arguments, 0)?)))
+}
+
+fn binary(function: &str, arguments: &[Value], operation: fn(f64, f64) -> f64) -> Result<Value, String> {
+    Ok(Value::Number(operation(number(function, arguments, 0)?, number(function, arguments, 1)?)))
+}
+
+fn sqrt(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("sqrt", arguments, f64::sqrt)
+}
+
+fn pow(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    binary("pow", arguments, f64::powf)
+}
+
+fn floor(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("floor", arguments, f64::floor)
+}
+
+fn ceil(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("ceil", arguments, f64::ceil)
+}
+
+/// Rounds half-way cases away from zero.
+fn round(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("round", arguments, f64::round)
+}
+
+fn abs(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("abs", arguments, f64::abs)
+}
+
+fn min(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    binary("min", arguments, f64::min)
+}
+
+fn max(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    binary("max", arguments, f64::max)
+}
+
+fn sin(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("sin", arguments, f64::sin)
+}
+
+fn cos(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("cos", arguments, f64::cos)
+}
+
+fn tan(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("tan", arguments, f64::tan)
+}
+
+fn asin(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("asin", arguments, f64::asin)
+}
+
+fn acos(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("acos", arguments, f64::acos)
+}
+
+fn atan(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    unary("atan", arguments, f64::atan)
+}
+
+/// The angle of the point `(x, y)`, called as `atan2(y, x)`.
+fn atan2(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    binary("atan2", arguments, f64::atan2)
+}
+
+/// A number in `[0, 1)` from the interpreter's seeded generator.
+fn random(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(interpreter.random()))
+}
+
+// The argument at `index` as a path inside the interpreter's sandbox.
+fn path(function: &str, interpreter: &Interpreter, arguments: &[Value], index: usize) -> Result<PathBuf, String> {
+    interpreter.sandbox().resolve(string(function, arguments, index)?)
+}
+
+fn io_error(action: &str, arguments: &[Value], err: std::io::Error) -> String {
+    format!("Can't {} '{}': {}", action, arguments[0], err)
+}
+
+fn read_file(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let path = path("read_file", interpreter, arguments, 0)?;
+    let contents = fs::read_to_string(path).map_err(|err| io_error("read", arguments, err))?;
+    Ok(Value::String(contents))
+}
+
+/// Replaces the contents of a file, creating it if needed.
+fn write_file(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let path = path("write_file", interpreter, arguments, 0)?;
+    let contents = string("write_file", arguments, 1)?;
+    fs::write(path, contents).map_err(|err| io_error("write", arguments, err))?;
+    Ok(Value::Nil)
+}
+
+/// Adds to the end of a file, creating it if needed.
+fn append_file(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let path = path("append_file", interpreter, arguments, 0)?;
+    let contents = string("append_file", arguments, 1)?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|err| io_error("write", arguments, err))?;
+    Ok(Value::Nil)
+}
+
+/// The lines of a file without their terminators.
+fn read_lines(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let path = path("read_lines", interpreter, arguments, 0)?;
+    let contents = fs::read_to_string(path).map_err(|err| io_error("read", arguments, err))?;
+    Ok(Value::List(contents.lines().map(|line| Value::String(line.to_string())).collect()))
+}
+
+fn file_exists(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let path = path("file_exists", interpreter, arguments, 0)?;
+    Ok(Value::Boolean(path.exists()))
+}
+
+/// The names of the entries in a directory, sorted.
+fn list_dir(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let path = path("list_dir", interpreter, arguments, 0)?;
+    let mut names = fs::read_dir(path)
+        .and_then(|entries| {
+            entries.map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned())).collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|err| io_error("list", arguments, err))?;
+    names.sort();
+    Ok(Value::List(names.into_iter().map(Value::String).collect()))
+}
+
+/// Prints a prompt, which must be a string, then reads a line like
+/// `read_line`.
+fn input(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, String> {
+    let prompt = string("input", arguments, 0)?;
+    interpreter.write_prompt(prompt).map_err(|err| format!("Can't write prompt: {}", err))?;
+    read_line(interpreter, &[])
+}
+
+/// The next line of input without its terminator, or nil at the end of input.
+fn read_line(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value, String> {
+    let line = interpreter.read_line().map_err(|err| format!("Can't read input: {}", err))?;
+    Ok(line.map_or(Value::Nil, Value::String))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::error::LoxError;
+
+    fn run(source: &str) -> Result<String, LoxError> {
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let program = crate::parse(source).unwrap();
+        Interpreter::new(output).interpret(&program)?;
+        Ok(String::from_utf8(output_buffer).unwrap())
+    }
+
+    #[test]
+    fn string_natives() {
+        let source = r#"
+            var s = "héllo wörld";
+            print len(s);
+            print substr(s, 1, 5);
+            print index_of(s, "wö");
+            print index_of(s, "x");
+            print join(split(s, " "), ", ");
+            print upper(s) + lower("ABC");
+            print "[" + trim("  a b ") + "]";
+            print replace(s, "l", "L");
+            print starts_with(s, "hé");
+            print chars("añ");
+        "#;
+        assert_eq!(
+            run(source).unwrap(),
+            "11\néllo\n6\n-1\nhéllo, wörld\nHÉLLO WÖRLDabc\n[a b]\nhéLLo wörLd\ntrue\n[a, ñ]\n",
+        );
+    }
+
+    #[test]
+    fn reject_bad_string_arguments() {
+        let errors = [
+            ("substr(\"abc\", 2, 4);", "Range 2..4 is out of bounds for a string of length 3."),
+            ("substr(\"abc\", 0, 99999999999999999999);", "Range 0..100000000000000000000 is out of bounds for a string of length 3."),
+            ("get(split(\"a\", \",\"), 18446744073709551616);", "Index 18446744073709552000 is out of bounds for a list of length 1."),
+            ("substr(\"abc\", 1.5, 2);", "Argument 2 to 'substr' must be a non-negative integer, got 1.5."),
+            ("get(split(\"a\", \",\"), -1);", "Argument 2 to 'get' must be a non-negative integer, got -1."),
+            ("upper(1);", "Argument 1 to 'upper' must be a string, got number."),
+            ("len(nil);", "Argument 1 to 'len' must be a list or a string, got nil."),
+            ("split(\"a\", \"\");", "Separator for 'split' must not be empty."),
+            ("input(nil);", "Argument 1 to 'input' must be a string, got nil."),
+        ];
+        for (source, message) in errors {
+            assert_eq!(run(source).unwrap_err().to_string(), format!("{}\n[line 1]", message));
+        }
+    }
+
+    #[test]
+    fn math_natives() {
+        let source = "print sqrt(16); print pow(2, 10); print floor(-1.5); print ceil(1.2); print round(2.5); \
+            print abs(-3); print min(1, 2); print max(1, 2); print cos(PI); print atan2(1, 1) * 4 == PI; print floor(E);";
+        assert_eq!(run(source).unwrap(), "4\n1024\n-2\n2\n3\n3\n1\n2\n-1\ntrue\n2\n");
+        assert_eq!(
+            run("print sqrt(\"4\");").unwrap_err().to_string(),
+            "Argument 1 to 'sqrt' must be a number, got string.\n[line 1]",
+        );
+    }
+
+    #[test]
+    fn seeded_random() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        for _ in 0..100 {
+            let number = first.next_f64();
+            assert!((0.0..1.0).contains(&number));
+            assert_eq!(number, second.next_f64());
+        }
+        assert_ne!(Random::new(1).next_f64(), Random::new(2).next_f64());
+    }
+
+    #[test]
+    fn file_natives() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = format!(
+            "var dir = \"{}\";
+            var path = dir + \"/report.txt\";
+            print file_exists(path);
+            write_file(path, \"a\n\");
+            append_file(path, \"b\n\");
+            print read_file(path) == \"a\nb\n\";
+            print read_lines(path);
+            print list_dir(dir);
+            read_file(\"{}\");",
+            dir.path().display(),
+            dir.path().join("../outside.txt").display(),
+        );
+        let mut output_buffer = Vec::new();
+        let output: Rc<Mutex<&mut dyn Write>> = Rc::new(Mutex::new(&mut output_buffer));
+        let program = crate::parse(&source).unwrap();
+        let mut interpreter = Interpreter::new(output);
+        interpreter.allow_dir(dir.path());
+        let err = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Access to '{}' is not allowed.\n[line 13]", dir.path().join("../outside.txt").display()),
+        );
+        drop(interpreter);
+        assert_eq!(String::from_utf8(output_buffer).unwrap(), "false\ntrue\n[a, b]\n[report.txt]\n");
+        assert_eq!(
+            run("read_file(\"ast.schema\");").unwrap_err().to_string(),
+            "Access to 'ast.schema' is not allowed.\n[line 1]",
+        );
+    }
+}
@@ -1,40 +1,194 @@
 use std::cell::RefCell;
 use crate::{
-    error::LoxError,
-    expr::{Binary, Expr, Grouping, Literal, Unary},
+    error::{Diagnostic, LoxError},
+    expr::{Assign, Binary, Call, Conditional, Expr, Grouping, Literal, Unary, Variable},
+    stmt::{Block, Expression, Print, Stmt, Var, While},
     token::{Literal as LoxLiteral, Token, TokenType},
 };
 
+const MAX_ARGUMENTS: usize = 255;
+
+/// The statements of a successfully parsed source file, in source order.
+pub struct Program<'a> {
+    pub statements: Vec<Stmt<'a>>,
+}
+
 pub struct Parser<'a> {
-    tokens: &'a Vec<Token<'a>>,
+    tokens: Vec<Token<'a>>,
     current: RefCell<usize>,
+    // Token types checked at the current position, reported when it turns out
+    // to be an error.
+    expected: RefCell<Vec<TokenType>>,
+    // Opening parentheses that have not been closed yet.
+    open_parens: RefCell<Vec<Token<'a>>>,
+    // Errors the parser recovered from without unwinding the statement.
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    // Whether a condition such as that of a `while` is being parsed, where a
+    // stray '=' was most likely meant to be '=='.
+    in_condition: RefCell<bool>,
+    // Whether the source is a line typed into the REPL, where the final ';'
+    // may be left out.
+    repl: bool,
 }
 
-fn error(token: Token, message: String) -> LoxError {
-    if token.token_type == TokenType::EOF {
-        LoxError::ParseError { line: token.line as usize, where_: String::from(" at end"), message }
-    } else {
-        LoxError::ParseError { line: token.line as usize, where_: format!(" at '{}'", token.lexeme), message }
+impl<'a> Parser<'a> {
+    /// Creates a parser over `tokens`, which must be terminated by an EOF token
+    /// as produced by [`crate::scanner::Scanner::scan_tokens`].
+    pub fn new(tokens: Vec<Token<'a>>) -> Self {
+        Self {
+            tokens,
+            current: RefCell::new(0),
+            expected: RefCell::new(Vec::new()),
+            open_parens: RefCell::new(Vec::new()),
+            diagnostics: RefCell::new(Vec::new()),
+            in_condition: RefCell::new(false),
+            repl: false,
+        }
     }
-}
 
-impl<'a> Parser<'a> {
-    fn new(tokens: &'a Vec<Token<'a>>) -> Self {
-        Self { tokens, current: RefCell::new(0) }
+    /// Lets the last statement of the source omit its terminating ';', as
+    /// is convenient for input typed into the REPL.
+    pub fn for_repl(self) -> Self {
+        Self { repl: true, ..self }
+    }
+
+    /// Parses the whole token stream. On a syntax error the parser skips to the
+    /// next statement boundary and keeps going, so every error is reported.
+    pub fn parse(&self) -> Result<Program<'a>, Vec<Diagnostic>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    self.diagnostics.borrow_mut().push(Diagnostic::from(err));
+                    self.open_parens.borrow_mut().clear();
+                    self.synchronize();
+                }
+            }
+        }
+        let diagnostics = self.diagnostics.take();
+        if diagnostics.is_empty() {
+            Ok(Program { statements })
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    fn declaration(&self) -> Result<Stmt<'a>, LoxError> {
+        if self.match_(&[&TokenType::Var]) {
+            return self.var_declaration();
+        }
+        self.statement()
+    }
+
+    fn var_declaration(&self) -> Result<Stmt<'a>, LoxError> {
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+        let initializer = if self.match_(&[&TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume_semicolon("Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var(Var::new(name, initializer)))
+    }
+
+    fn statement(&self) -> Result<Stmt<'a>, LoxError> {
+        if self.match_(&[&TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_(&[&TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_(&[&TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(Block::new(self.block()?)));
+        }
+        self.expression_statement()
+    }
+
+    fn while_statement(&self) -> Result<Stmt<'a>, LoxError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        self.open_parens.borrow_mut().push(self.previous());
+        let condition = self.condition()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        self.open_parens.borrow_mut().pop();
+        let body = self.statement()?;
+        Ok(Stmt::While(While::new(condition, body)))
+    }
+
+    fn condition(&self) -> Result<Expr<'a>, LoxError> {
+        let outer = self.in_condition.replace(true);
+        let condition = self.expression();
+        self.in_condition.replace(outer);
+        condition
+    }
+
+    fn block(&self) -> Result<Vec<Stmt<'a>>, LoxError> {
+        let mut statements = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn print_statement(&self) -> Result<Stmt<'a>, LoxError> {
+        let value = self.expression()?;
+        self.consume_semicolon("Expect ';' after value.")?;
+        Ok(Stmt::Print(Print::new(value)))
+    }
+
+    fn expression_statement(&self) -> Result<Stmt<'a>, LoxError> {
+        let expr = self.expression()?;
+        self.consume_semicolon("Expect ';' after expression.")?;
+        Ok(Stmt::Expression(Expression::new(expr)))
+    }
+
+    fn expression(&self) -> Result<Expr<'a>, LoxError> {
+        self.comma()
     }
 
-    fn parse(&self) -> Option<Expr> {
-        match self.expression() {
-            Ok(expr) => Some(expr),
-            Err(_) => None,
+    fn comma(&self) -> Result<Expr<'a>, LoxError> {
+        let mut expr = self.assignment()?;
+        while self.match_(&[&TokenType::Comma]) {
+            let operator = self.previous();
+            let right = self.assignment()?;
+            expr = Expr::Binary(Binary::new(expr, operator, right));
         }
+        Ok(expr)
     }
 
-    fn expression(&self) -> Result<Expr, LoxError> {
-        return self.equality();
+    fn assignment(&self) -> Result<Expr<'a>, LoxError> {
+        let expr = self.conditional()?;
+        if self.match_(&[&TokenType::Equal]) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+            if let Expr::Variable(variable) = expr {
+                return Ok(Expr::Assign(Assign::new(variable.name, value)));
+            }
+            // Nothing to synchronize: the parser is not confused, the target
+            // just can't be assigned to. In `a + 1 = 2 ? x : y` the '=' ends
+            // up here too, though it belongs to the conditional's condition.
+            let mut diagnostic = self.recovered_error(equals, "Invalid assignment target.");
+            if *self.in_condition.borrow() || matches!(value, Expr::Conditional(_)) {
+                diagnostic.hint = Some(String::from("Did you mean '==' to compare values?"));
+            }
+            self.diagnostics.borrow_mut().push(diagnostic);
+        }
+        Ok(expr)
+    }
+
+    fn conditional(&self) -> Result<Expr<'a>, LoxError> {
+        let expr = self.equality()?;
+        if self.match_(&[&TokenType::Question]) {
+            let then_branch = self.expression()?;
+            self.consume(TokenType::Colon, "Expect ':' after then branch of conditional expression.")?;
+            let else_branch = self.conditional()?;
+            return Ok(Expr::Conditional(Conditional::new(expr, then_branch, else_branch)));
+        }
+        Ok(expr)
     }
 
-    fn equality(&self) -> Result<Expr, LoxError> {
+    fn equality(&self) -> Result<Expr<'a>, LoxError> {
         let mut expr = self.comparison()?;
         while self.match_(&[&TokenType::BangEqual, &TokenType::EqualEqual]) {
             let operator = self.previous();
@@ -44,7 +198,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn comparison(&self) -> Result<Expr, LoxError> {
+    fn comparison(&self) -> Result<Expr<'a>, LoxError> {
         let mut expr = self.term()?;
         while self.match_(&[
             &TokenType::Greater,
@@ -59,7 +213,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn term(&self) -> Result<Expr, LoxError> {
+    fn term(&self) -> Result<Expr<'a>, LoxError> {
         let mut expr = self.factor()?;
         while self.match_(&[&TokenType::Plus, &TokenType::Minus]) {
             let operator = self.previous();
@@ -69,7 +223,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn factor(&self) -> Result<Expr, LoxError> {
+    fn factor(&self) -> Result<Expr<'a>, LoxError> {
         let mut expr = self.unary()?;
         while self.match_(&[&TokenType::Star, &TokenType::Slash]) {
             let operator = self.previous();
@@ -79,16 +233,47 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn unary(&self) -> Result<Expr, LoxError> {
-        while self.match_(&[&TokenType::Bang, &TokenType::Minus]) {
+    fn unary(&self) -> Result<Expr<'a>, LoxError> {
+        if self.match_(&[&TokenType::Bang, &TokenType::Minus]) {
             let operator = self.previous();
             let right = self.unary()?;
             return Ok(Expr::Unary(Unary::new(operator, right)));
         }
-        self.primary()
+        self.call()
     }
 
-    fn primary(&self) -> Result<Expr, LoxError> {
+    fn call(&self) -> Result<Expr<'a>, LoxError> {
+        let mut expr = self.primary()?;
+        while self.match_(&[&TokenType::LeftParen]) {
+            expr = self.finish_call(expr)?;
+        }
+        Ok(expr)
+    }
+
+    // Arguments are parsed below the comma operator, whose `,` would
+    // otherwise swallow the separators.
+    fn finish_call(&self, callee: Expr<'a>) -> Result<Expr<'a>, LoxError> {
+        self.open_parens.borrow_mut().push(self.previous());
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() == MAX_ARGUMENTS {
+                    let message = format!("Can't have more than {} arguments.", MAX_ARGUMENTS);
+                    let diagnostic = self.recovered_error(self.peek(), &message);
+                    self.diagnostics.borrow_mut().push(diagnostic);
+                }
+                arguments.push(self.assignment()?);
+                if !self.match_(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        self.open_parens.borrow_mut().pop();
+        Ok(Expr::Call(Call::new(callee, paren, arguments)))
+    }
+
+    fn primary(&self) -> Result<Expr<'a>, LoxError> {
         if self.match_(&[&TokenType::False]) {
             return Ok(Expr::Literal(Literal::new(LoxLiteral::Boolean(false))));
         }
@@ -101,12 +286,48 @@ impl<'a> Parser<'a> {
         if self.match_(&[&TokenType::Number, &TokenType::String]) {
             return Ok(Expr::Literal(Literal::new(self.previous().literal)));
         }
+        if self.match_(&[&TokenType::Identifier]) {
+            return Ok(Expr::Variable(Variable::new(self.previous())));
+        }
         if self.match_(&[&TokenType::LeftParen]) {
+            self.open_parens.borrow_mut().push(self.previous());
             let expr = self.expression()?;
             self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+            self.open_parens.borrow_mut().pop();
             return Ok(Expr::Grouping(Grouping::new(expr)));
         }
-        Err(error(self.peek(), String::from("Expect expression.")))
+        if let Some(expr) = self.missing_left_operand()? {
+            return Ok(expr);
+        }
+        Err(self.error(self.peek(), "Expect expression."))
+    }
+
+    // Error productions: a binary operator at the start of an expression. The
+    // error is recorded and the right-hand operand, parsed at the operator's
+    // precedence, stands in for the whole expression so parsing can go on.
+    fn missing_left_operand(&self) -> Result<Option<Expr<'a>>, LoxError> {
+        let operand: fn(&Self) -> Result<Expr<'a>, LoxError> = match self.peek().token_type {
+            TokenType::Comma => Self::assignment,
+            TokenType::BangEqual | TokenType::EqualEqual => Self::comparison,
+            TokenType::Greater |
+            TokenType::GreaterEqual |
+            TokenType::Less |
+            TokenType::LessEqual => Self::term,
+            TokenType::Plus => Self::factor,
+            TokenType::Star | TokenType::Slash => Self::unary,
+            _ => return Ok(None),
+        };
+        let operator = self.peek();
+        let message = format!("Binary operator '{}' requires a left operand.", operator.lexeme);
+        let index = {
+            let mut diagnostics = self.diagnostics.borrow_mut();
+            diagnostics.push(self.recovered_error(operator.clone(), &message));
+            diagnostics.len() - 1
+        };
+        self.advance();
+        let right = operand(self)?;
+        self.diagnostics.borrow_mut()[index].span = operator.span.to(self.previous().span);
+        Ok(Some(right))
     }
 
     fn match_(&self, types: &[&TokenType]) -> bool {
@@ -120,6 +341,11 @@ impl<'a> Parser<'a> {
     }
 
     fn check(&self, token_type: &TokenType) -> bool {
+        let mut expected = self.expected.borrow_mut();
+        if !expected.contains(token_type) {
+            expected.push(token_type.clone());
+        }
+        drop(expected);
         if self.is_at_end() {
             false
         } else {
@@ -132,7 +358,8 @@ impl<'a> Parser<'a> {
             let mut current = self.current.borrow_mut();
             *current += 1;
         }
-        return self.previous();
+        self.expected.borrow_mut().clear();
+        self.previous()
     }
 
     fn is_at_end(&self) -> bool {
@@ -140,7 +367,6 @@ impl<'a> Parser<'a> {
     }
 
     fn peek(&self) -> Token<'a> {
-        println!("{:?}", self.current);
         self.tokens[self.current.borrow().to_owned()].clone()
     }
 
@@ -148,12 +374,62 @@ impl<'a> Parser<'a> {
         self.tokens[self.current.borrow().to_owned() - 1].clone()
     }
 
-    fn consume(&self, token_type: TokenType, message: &'a str) -> Result<Token<'a>, LoxError> {
+    fn consume(&self, token_type: TokenType, message: &str) -> Result<Token<'a>, LoxError> {
         if self.check(&token_type) {
             Ok(self.advance())
         } else {
-            Err(error(self.peek(), message.to_string()))
+            Err(self.error(self.peek(), message))
+        }
+    }
+
+    fn consume_semicolon(&self, message: &str) -> Result<(), LoxError> {
+        if !(self.repl && self.is_at_end()) {
+            self.consume(TokenType::Semicolon, message)?;
         }
+        Ok(())
+    }
+
+    fn error(&self, token: Token<'a>, message: &str) -> LoxError {
+        let where_ = if token.token_type == TokenType::EOF {
+            String::from(" at end")
+        } else {
+            format!(" at '{}'", token.lexeme)
+        };
+        let expected = self.expected.take();
+        let hint = self.suggest(&token, &expected);
+        LoxError::ParseError {
+            line: token.line as usize,
+            where_,
+            message: message.to_string(),
+            span: token.span,
+            expected,
+            hint,
+        }
+    }
+
+    // An error about a construct that parsed fine but is not allowed, rather
+    // than a missing token, so the token types checked so far don't apply.
+    fn recovered_error(&self, token: Token<'a>, message: &str) -> Diagnostic {
+        self.expected.borrow_mut().clear();
+        Diagnostic::from(self.error(token, message))
+    }
+
+    fn suggest(&self, token: &Token<'a>, expected: &[TokenType]) -> Option<String> {
+        if expected.contains(&TokenType::RightParen) {
+            if let Some(open_paren) = self.open_parens.borrow().last() {
+                return Some(format!("The '(' opened on line {} is never closed.", open_paren.line));
+            }
+        }
+        if token.token_type == TokenType::RightParen && self.open_parens.borrow().is_empty() {
+            return Some(String::from("This ')' has no matching '('."));
+        }
+        if expected.contains(&TokenType::Semicolon) && *self.current.borrow() > 0 {
+            let previous = self.previous();
+            if token.line > previous.line || token.token_type == TokenType::EOF {
+                return Some(format!("Did you forget a ';' at the end of line {}?", previous.line));
+            }
+        }
+        None
     }
 
     fn synchronize(&self) {
@@ -168,7 +444,7 @@ impl<'a> Parser<'a> {
                 TokenType::Var |
                 TokenType::For |
                 TokenType::If |
-                TokenType:: While |
+                TokenType::While |
                 TokenType::Print |
                 TokenType::Return => return,
                 _ => self.advance(),
@@ -179,7 +455,7 @@ impl<'a> Parser<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{token, ast_printer::AstPrinter};
+    use crate::{token, ast_printer::{AstPrinter, ExprPrinter}};
     use super::*;
 
     #[test]
@@ -193,10 +469,178 @@ mod tests {
             token::Token::new(TokenType::RightParen, ")", LoxLiteral::Nil, 1),
             token::Token::new(TokenType::EOF, "", LoxLiteral::Nil, 1),
         ];
-        let parser = Parser::new(&tokens);
-        let expr = parser.parse().unwrap();
+        let parser = Parser::new(tokens);
+        let expr = parser.expression().unwrap();
         let mut printer: AstPrinter = AstPrinter;
         let result = printer.print(&expr);
         assert_eq!(result, "(* (- 123) (group 45.67))");
     }
+
+    #[test]
+    fn parse_program() {
+        let program = crate::parse("print 1 + 2;\n-3;\nvar a;\n{ var b = 4; }").unwrap();
+        let mut printer: AstPrinter = AstPrinter;
+        let printed: Vec<String> = program.statements.iter().map(|stmt| match stmt {
+            Stmt::Print(print) => format!("print {}", printer.print(&print.expression)),
+            Stmt::Expression(expression) => printer.print(&expression.expression),
+            Stmt::Var(var) => format!("var {} {:?}", var.name.lexeme, var.initializer.as_ref().map(|expr| printer.print(expr))),
+            Stmt::Block(block) => format!("block of {}", block.statements.len()),
+            Stmt::While(_) => panic!("Invalid statement"),
+        }).collect();
+        assert_eq!(printed, vec!["print (+ 1 2)", "(- 3)", "var a None", "block of 1"]);
+    }
+
+    #[test]
+    fn parse_reports_every_error() {
+        let diagnostics = crate::parse("print (1;\n2 +;\nprint 3").err().unwrap();
+        let messages: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+        assert_eq!(messages, vec![
+            "Expect ')' after expression.",
+            "Expect expression.",
+            "Expect ';' after value.",
+        ]);
+        assert_eq!(diagnostics[1].to_string(), "[line 2] Error at ';': Expect expression.\n  \
+            expected one of '!', '-', 'false', 'true', 'nil', number, string, identifier, '('");
+    }
+
+    #[test]
+    fn parse_reports_scan_error() {
+        let diagnostics = crate::parse("print \"abc;").err().unwrap();
+        assert_eq!(diagnostics, vec![Diagnostic {
+            line: 1,
+            where_: String::new(),
+            message: String::from("Unterminated string."),
+            span: token::Span::new(6, 11),
+            expected: Vec::new(),
+            hint: None,
+        }]);
+    }
+
+    fn hint(source: &str) -> Option<String> {
+        crate::parse(source).err().unwrap().remove(0).hint
+    }
+
+    #[test]
+    fn suggest_missing_semicolon() {
+        assert_eq!(hint("print 1\nprint 2;"), Some(String::from("Did you forget a ';' at the end of line 1?")));
+        assert_eq!(hint("1 + 2"), Some(String::from("Did you forget a ';' at the end of line 1?")));
+    }
+
+    #[test]
+    fn suggest_equality_operator() {
+        let equality = Some(String::from("Did you mean '==' to compare values?"));
+        let diagnostic = crate::parse("while (a + 1 = 2) {}").err().unwrap().remove(0);
+        assert_eq!(diagnostic.where_, " at '='");
+        assert_eq!(diagnostic.message, "Invalid assignment target.");
+        assert!(diagnostic.expected.is_empty());
+        assert_eq!(diagnostic.hint, equality);
+        assert_eq!(hint("print a + 1 = 2 ? 3 : 4;"), equality);
+        assert_eq!(hint("var = 1;"), None);
+        assert_eq!(hint("a + b = c;"), None);
+        assert_eq!(hint("print 1 = 2;"), None);
+    }
+
+    #[test]
+    fn suggest_unbalanced_parens() {
+        assert_eq!(hint("print (1 +\n(2;"), Some(String::from("The '(' opened on line 2 is never closed.")));
+        assert_eq!(hint("print 1);"), Some(String::from("This ')' has no matching '('.")));
+    }
+
+    fn print_expression(source: &str) -> String {
+        let program = crate::parse(source).unwrap();
+        match &program.statements[0] {
+            Stmt::Expression(expression) => AstPrinter.print(&expression.expression),
+            _ => panic!("Invalid statement"),
+        }
+    }
+
+    #[test]
+    fn parse_conditional() {
+        assert_eq!(print_expression("0 == 1 ? 2 : 3;"), "(?: (== 0 1) 2 3)");
+        assert_eq!(print_expression("1 ? 2 : 3 ? 4 : 5;"), "(?: 1 2 (?: 3 4 5))");
+        assert_eq!(print_expression("1 ? 2, 3 : 4;"), "(?: 1 (, 2 3) 4)");
+    }
+
+    #[test]
+    fn parse_comma() {
+        assert_eq!(print_expression("1, 2 ? 3 : 4, 5;"), "(, (, 1 (?: 2 3 4)) 5)");
+    }
+
+    #[test]
+    fn parse_call() {
+        assert_eq!(print_expression("f(1, 2 ? 3 : 4)(g);"), "(call (call f 1 (?: 2 3 4)) g)");
+        assert_eq!(print_expression("-f();"), "(- (call f))");
+    }
+
+    #[test]
+    fn parse_assignment() {
+        assert_eq!(print_expression("a = b = 1, c;"), "(, (= a (= b 1)) c)");
+        let diagnostics = crate::parse("a + b = 1;").err().unwrap();
+        assert_eq!(diagnostics[0].message, "Invalid assignment target.");
+        assert_eq!(diagnostics[0].where_, " at '='");
+        assert!(diagnostics[0].expected.is_empty());
+    }
+
+    #[test]
+    fn parse_repl_input() {
+        let tokens = crate::scanner::Scanner::new("var a = 1; a + 2").scan_tokens().unwrap();
+        let program = Parser::new(tokens).for_repl().parse().unwrap();
+        assert_eq!(program.statements.len(), 2);
+        assert!(crate::parse("var a = 1; a + 2").is_err());
+        let tokens = crate::scanner::Scanner::new("1 2").scan_tokens().unwrap();
+        assert!(Parser::new(tokens).for_repl().parse().is_err());
+    }
+
+    #[test]
+    fn parse_while() {
+        let program = crate::parse("while (a) { a = false; }").unwrap();
+        match &program.statements[0] {
+            Stmt::While(stmt) => {
+                assert_eq!(AstPrinter.print(&stmt.condition), "a");
+                assert!(matches!(stmt.body.as_ref(), Stmt::Block(_)));
+            },
+            _ => panic!("Invalid statement"),
+        }
+        let diagnostics = crate::parse("while (a b;").err().unwrap();
+        assert_eq!(diagnostics[0].message, "Expect ')' after condition.");
+    }
+
+    #[test]
+    fn parse_missing_left_operand() {
+        let diagnostics = crate::parse("* 3;\n== 1 + 2;\nprint 4;").err().unwrap();
+        let errors: Vec<(usize, &str, &str)> = diagnostics.iter().map(|diagnostic| {
+            (diagnostic.line, diagnostic.where_.as_str(), diagnostic.message.as_str())
+        }).collect();
+        assert_eq!(errors, vec![
+            (1, " at '*'", "Binary operator '*' requires a left operand."),
+            (2, " at '=='", "Binary operator '==' requires a left operand."),
+        ]);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.expected.is_empty()));
+    }
+
+    #[test]
+    fn missing_left_operand_keeps_parsing() {
+        let diagnostics = crate::parse("print (/ 2 + 1;").err().unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "Binary operator '/' requires a left operand.");
+        assert_eq!(diagnostics[0].span, token::Span::new(7, 10));
+        assert_eq!(diagnostics[1].message, "Expect ')' after expression.");
+        assert_eq!(diagnostics[1].span, token::Span::new(14, 15));
+    }
+
+    #[test]
+    fn parse_tree_shape() {
+        use crate::token::StructuralEq;
+        let program = crate::parse("print -1 *\n  (2);").unwrap();
+        let number = |value| Expr::Literal(Literal::new(LoxLiteral::Number(value)));
+        let expected = Stmt::Print(Print::new(Expr::Binary(Binary::new(
+            Expr::Unary(Unary::new(Token::new(TokenType::Minus, "-", LoxLiteral::Nil, 1), number(1.0))),
+            Token::new(TokenType::Star, "*", LoxLiteral::Nil, 1),
+            Expr::Grouping(Grouping::new(number(2.0))),
+        ))));
+        assert!(program.statements[0].structural_eq(&expected));
+        assert_ne!(program.statements[0], expected);
+        assert_eq!(program.statements[0].clone(), program.statements[0]);
+        assert!(!program.statements[0].structural_eq(&Stmt::Expression(Expression::new(number(1.0)))));
+    }
 }
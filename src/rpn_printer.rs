@@ -0,0 +1,89 @@
+use super::ast_printer::ExprPrinter;
+use super::expr::{Visitor, Expr, Assign, Unary, Binary, Call, Conditional, Literal, Grouping, Variable};
+use super::token::TokenType;
+
+/// Prints expressions in Reverse Polish Notation. Unary minus is written as
+/// `neg` to tell it apart from subtraction, and groupings disappear since the
+/// operand order already encodes precedence. Calls carry their argument
+/// count, as in `a b f call/2`.
+pub struct RpnPrinter;
+
+impl Visitor<String> for RpnPrinter {
+    fn visit_unary_expr(&mut self, expr: &Unary) -> String {
+        let operator = match expr.operator.token_type {
+            TokenType::Minus => "neg",
+            _ => expr.operator.lexeme,
+        };
+        self.postfix(operator, &[expr.right.as_ref()])
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> String {
+        self.postfix(expr.operator.lexeme, &[expr.left.as_ref(), expr.right.as_ref()])
+    }
+
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        format!("{} {} =", expr.name.lexeme, self.print(expr.value.as_ref()))
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let mut exprs: Vec<&Expr> = expr.arguments.iter().collect();
+        exprs.push(expr.callee.as_ref());
+        self.postfix(&format!("call/{}", expr.arguments.len()), &exprs)
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> String {
+        let exprs = [expr.condition.as_ref(), expr.then_branch.as_ref(), expr.else_branch.as_ref()];
+        self.postfix("?:", &exprs)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        format!("{}", expr.value)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> String {
+        self.print(expr.expression.as_ref())
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
+}
+
+impl ExprPrinter for RpnPrinter {
+    fn print(&mut self, expr: &Expr) -> String {
+        expr.accept(self as &mut dyn Visitor<String>)
+    }
+}
+
+impl RpnPrinter {
+    fn postfix(&mut self, name: &str, exprs: &[&Expr]) -> String {
+        let mut result = String::new();
+        for expr in exprs {
+            result.push_str(&self.print(expr));
+            result.push(' ');
+        }
+        result.push_str(name);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(source: &str) -> String {
+        let program = crate::parse(source).unwrap();
+        match &program.statements[0] {
+            crate::stmt::Stmt::Expression(expression) => RpnPrinter.print(&expression.expression),
+            _ => panic!("Invalid statement"),
+        }
+    }
+
+    #[test]
+    fn print_rpn() {
+        assert_eq!(print("(1 + 2) * (4 - 3);"), "1 2 + 4 3 - *");
+        assert_eq!(print("-123 * (45.67);"), "123 neg 45.67 *");
+        assert_eq!(print("!true == false ? 1 : 2;"), "true ! false == 1 2 ?:");
+        assert_eq!(print("f(1, g());"), "1 g call/0 f call/2");
+    }
+}
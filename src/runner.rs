@@ -1,30 +1,241 @@
-use std::{io::Write, rc::Rc, sync::Mutex};
-use super::error::{ErrorReporter, LoxError};
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+    rc::Rc,
+    sync::{atomic::AtomicBool, Arc, Mutex, MutexGuard, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use super::ast_printer::AstFormat;
+use super::cli::Options;
+use super::error::{Diagnostic, ErrorReporter, LoxError};
+use super::interpreter::Interpreter;
+use super::scanner::Scanner;
+use super::expr::Expr;
+use super::stmt::Stmt;
 
-pub trait Runner {
+pub trait Runner<'a> {
     fn run(&mut self, source: String) -> Result<(), LoxError>;
+
+    /// Gives the runner the input the driver reads from, so that programs
+    /// can read from it too. Runners whose programs don't read input ignore
+    /// it.
+    fn set_input(&mut self, _input: Rc<Mutex<&'a mut dyn BufRead>>) {}
+
+    /// Runs a line typed into the REPL.
+    fn run_repl(&mut self, source: String) -> Result<(), LoxError> {
+        self.run(source)
+    }
+
+    /// Runs a line typed into the REPL as if `mode` had been configured.
+    fn run_with_mode(&mut self, source: String, _mode: Mode) -> Result<(), LoxError> {
+        self.run_repl(source)
+    }
+
+    /// The global variables defined so far and their values, sorted by name.
+    fn globals(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Forgets all state built up by the programs run so far.
+    fn reset(&mut self) {}
+
+    /// Applies the command-line options, such as what `run` does with a
+    /// program. Runners that only support running programs ignore them.
+    fn configure(&mut self, _options: &Options) {}
+}
+
+/// What a runner does with the source it is given.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Mode {
+    /// Execute the program.
+    #[default]
+    Run,
+    /// Print the tokens of the program, one per line.
+    DumpTokens,
+    /// Print the expression of each statement in the given format.
+    DumpAst(AstFormat),
+    /// Only report syntax errors.
+    Check,
 }
 
 pub struct LoxRunner<'a> {
     had_error: bool,
     output: Rc<Mutex<&'a mut dyn Write>>,
+    mode: Mode,
+    interpreter: Interpreter<'a>,
 }
 
 impl<'a> LoxRunner<'a> {
     pub fn new(output: Rc<Mutex<&'a mut dyn Write>>) -> Self {
         Self {
             had_error: false,
+            interpreter: Interpreter::new(output.clone()),
             output,
+            mode: Mode::Run,
         }
     }
+
+    pub fn with_mode(self, mode: Mode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Lets code being run be interrupted by setting `interrupt`, e.g. from
+    /// a SIGINT handler.
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interpreter.set_interrupt(interrupt);
+        self
+    }
+
+    /// Seeds the generator behind the `random()` native.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.interpreter.set_seed(seed);
+        self
+    }
+
+    /// Lets programs read and write files in `dir` and below it. Without
+    /// any allowed directory the file natives fail.
+    pub fn with_allowed_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.interpreter.allow_dir(dir);
+        self
+    }
+
+    // Poisoning is ignored, as in `Lox`.
+    fn output(&self) -> MutexGuard<'_, &'a mut dyn Write> {
+        self.output.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn dump_tokens(&mut self, source: &str) -> Result<(), LoxError> {
+        match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => {
+                let mut output = self.output();
+                for token in tokens {
+                    writeln!(output, "{}", token)?;
+                }
+            },
+            Err(err) => {
+                self.had_error = true;
+                writeln!(self.output(), "{}", Diagnostic::from(err))?;
+            },
+        }
+        Ok(())
+    }
 }
 
-impl<'a> Runner for LoxRunner<'a> {
+// A different seed for every run when none is given.
+fn clock_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+// The top-level expressions of `stmt` and of the statements nested in it, in
+// source order.
+fn expressions<'s, 'a>(stmt: &'s Stmt<'a>, exprs: &mut Vec<&'s Expr<'a>>) {
+    match stmt {
+        Stmt::Block(block) => {
+            for statement in &block.statements {
+                expressions(statement, exprs);
+            }
+        },
+        Stmt::Expression(expression) => exprs.push(&expression.expression),
+        Stmt::Print(print) => exprs.push(&print.expression),
+        Stmt::Var(var) => exprs.extend(var.initializer.as_ref()),
+        Stmt::While(stmt) => {
+            exprs.push(&stmt.condition);
+            expressions(&stmt.body, exprs);
+        },
+    }
+}
+
+impl<'a> Runner<'a> for LoxRunner<'a> {
     fn run(&mut self, source: String) -> Result<(), LoxError> {
-        let mut output = self.output.lock().unwrap();
-        output.write_all(source.as_bytes())?;
-        output.write_all(b"\n")?;
-        output.flush()?;
+        self.run_source(source, false)
+    }
+
+    // Programs read lines with `input()` and `read_line()`, taking turns with
+    // the driver.
+    fn set_input(&mut self, input: Rc<Mutex<&'a mut dyn BufRead>>) {
+        self.interpreter.set_input(input);
+    }
+
+    fn run_repl(&mut self, source: String) -> Result<(), LoxError> {
+        self.run_source(source, true)
+    }
+
+    fn run_with_mode(&mut self, source: String, mode: Mode) -> Result<(), LoxError> {
+        let previous = std::mem::replace(&mut self.mode, mode);
+        let result = self.run_source(source, true);
+        self.mode = previous;
+        result
+    }
+
+    fn globals(&self) -> Vec<(String, String)> {
+        self.interpreter.globals().into_iter().map(|(name, value)| (name, value.to_string())).collect()
+    }
+
+    fn reset(&mut self) {
+        self.interpreter.reset();
+    }
+
+    fn configure(&mut self, options: &Options) {
+        self.mode = options.mode;
+        self.interpreter.set_args(options.args.clone());
+        self.interpreter.set_seed(options.seed.unwrap_or_else(clock_seed));
+        for dir in &options.allowed_dirs {
+            self.interpreter.allow_dir(dir);
+        }
+    }
+}
+
+impl<'a> LoxRunner<'a> {
+    // Syntax errors are reported here. Outside the REPL they also fail the
+    // run, so that the driver can exit with the right status.
+    fn run_source(&mut self, source: String, repl: bool) -> Result<(), LoxError> {
+        let result = if self.mode == Mode::DumpTokens {
+            self.dump_tokens(&source)
+        } else {
+            self.parse_and_run(&source, repl)
+        };
+        self.output().flush()?;
+        if std::mem::take(&mut self.had_error) && !repl {
+            return Err(LoxError::CompileError);
+        }
+        result
+    }
+
+    fn parse_and_run(&mut self, source: &str, repl: bool) -> Result<(), LoxError> {
+        let program = if repl {
+            crate::parse_repl(source)
+        } else {
+            crate::parse(source)
+        };
+        match program {
+            Ok(program) => match self.mode {
+                Mode::Run if repl => {
+                    if let Some(value) = self.interpreter.interpret_repl(&program)? {
+                        writeln!(self.output(), "{}", value)?;
+                    }
+                },
+                Mode::Run => self.interpreter.interpret(&program)?,
+                Mode::DumpAst(ast_format) => {
+                    let mut printer = ast_format.printer();
+                    let mut output = self.output();
+                    let mut exprs = Vec::new();
+                    for statement in &program.statements {
+                        expressions(statement, &mut exprs);
+                    }
+                    for expr in exprs {
+                        writeln!(output, "{}", printer.print(expr))?;
+                    }
+                },
+                Mode::DumpTokens | Mode::Check => (),
+            },
+            Err(diagnostics) => {
+                self.had_error = true;
+                let mut output = self.output();
+                for diagnostic in diagnostics {
+                    writeln!(output, "{}", diagnostic)?;
+                }
+            },
+        }
         Ok(())
     }
 }
@@ -33,7 +244,7 @@ impl<'a> ErrorReporter for LoxRunner<'a> {
     fn report(&mut self, err: LoxError) -> Result<(), LoxError> {
         match err {
             LoxError::ParseError{..} => {
-                self.output.lock().unwrap().write_all(format!("{}", err).as_bytes())?;
+                self.output().write_all(format!("{}", err).as_bytes())?;
                 self.had_error = true;
                 Ok(())
             },
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+/// The directories a program may read and write through the file natives.
+/// Nothing is allowed until the host allows a directory.
+#[derive(Debug, Default, Clone)]
+pub struct Sandbox {
+    allowed: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// Allows access to `dir` and everything below it.
+    pub fn allow(&mut self, dir: impl Into<PathBuf>) {
+        self.allowed.push(dir.into());
+    }
+
+    /// The absolute path of `path` if it lies in an allowed directory, or the
+    /// message to report to the program.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let denied = || format!("Access to '{}' is not allowed.", path);
+        let resolved = canonicalize(Path::new(path)).ok_or_else(denied)?;
+        let allowed = self.allowed.iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|dir| resolved.starts_with(dir));
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(denied())
+        }
+    }
+}
+
+// Resolves symlinks and `..` so that a path cannot escape the sandbox. A file
+// that does not exist yet is resolved through its parent, unless it is a
+// dangling symlink that writing would follow.
+fn canonicalize(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
+    }
+    if path.symlink_metadata().is_ok() {
+        return None;
+    }
+    let name = path.file_name()?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(parent.canonicalize().ok()?.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_allowed_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let allowed = root.path().join("allowed");
+        std::fs::create_dir(&allowed).unwrap();
+        std::fs::write(root.path().join("secret.txt"), "").unwrap();
+        let path = |name: &str| allowed.join(name).to_str().unwrap().to_string();
+
+        let mut sandbox = Sandbox::default();
+        assert!(sandbox.resolve(&path("new.txt")).is_err());
+        sandbox.allow(&allowed);
+        assert_eq!(sandbox.resolve(&path("new.txt")), Ok(allowed.canonicalize().unwrap().join("new.txt")));
+        assert_eq!(
+            sandbox.resolve(&path("../secret.txt")),
+            Err(format!("Access to '{}' is not allowed.", path("../secret.txt"))),
+        );
+        assert!(sandbox.resolve(&path("missing/new.txt")).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.path().join("secret.txt"), allowed.join("link.txt")).unwrap();
+            assert!(sandbox.resolve(&path("link.txt")).is_err());
+        }
+    }
+}
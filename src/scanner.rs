@@ -1,7 +1,7 @@
-use crate::{token::{Token, TokenType, Literal}, error::LoxError};
+use crate::{token::{Token, TokenType, Literal, Span}, error::LoxError};
 use phf::phf_map;
 
-static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
+pub(crate) static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and" => TokenType::And,
     "class" => TokenType::Class,
     "else" => TokenType::Else,
@@ -20,6 +20,8 @@ static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "while" => TokenType::While,
 };
 
+pub(crate) const UNTERMINATED_STRING: &str = "Unterminated string.";
+
 pub struct Scanner<'a> {
     source: &'a str,
     tokens: Vec<Token<'a>>,
@@ -39,12 +41,17 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, LoxError> {
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token<'a>>, LoxError> {
+        // A `#!` line lets scripts be run directly, e.g. `#!/usr/bin/env lox`.
+        if self.source.starts_with("#!") {
+            self.comment()?;
+        }
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token()?;
         }
-        self.tokens.push(Token::new(TokenType::EOF, "", Literal::Nil, self.line));
+        let eof = Token::new(TokenType::EOF, "", Literal::Nil, self.line).with_span(self.current, self.current);
+        self.tokens.push(eof);
         Ok(self.tokens.clone())
     }
 
@@ -61,6 +68,8 @@ impl<'a> Scanner<'a> {
             '+' => self.add_token(TokenType::Plus, None),
             ';' => self.add_token(TokenType::Semicolon, None),
             '*' => self.add_token(TokenType::Star, None),
+            '?' => self.add_token(TokenType::Question, None),
+            ':' => self.add_token(TokenType::Colon, None),
             '!' => {
                 if self.match_('=') {
                     self.add_token(TokenType::BangEqual, None)
@@ -97,7 +106,10 @@ impl<'a> Scanner<'a> {
                 }
             }
             ' ' | '\r' | '\t' => Ok(()),
-            '\n' => Ok(self.line += 1),
+            '\n' => {
+                self.line += 1;
+                Ok(())
+            },
             '"' => self.string(),
             c => {
                 if self.is_digit(c) {
@@ -109,6 +121,9 @@ impl<'a> Scanner<'a> {
                         line: self.line as usize,
                         where_: "".to_string(),
                         message: "Unexpected character.".to_string(),
+                        span: Span::new(self.start, self.current),
+                        expected: Vec::new(),
+                        hint: None,
                     })
                 }
             }
@@ -121,13 +136,16 @@ impl<'a> Scanner<'a> {
             Token::new(token_type, text, literal, self.line)
         } else {
             Token::new(token_type, text, Literal::Nil, self.line)
-        };
-        Ok(self.tokens.push(token))
+        }.with_span(self.start, self.current);
+        self.tokens.push(token);
+        Ok(())
     }
 
+    // `current` is a byte offset, so it moves past the whole character.
     pub fn advance(&mut self) -> char {
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap_or_default()
+        let c = self.peek();
+        self.current += c.len_utf8();
+        c
     }
 
     fn is_at_end(&self) -> bool {
@@ -138,31 +156,26 @@ impl<'a> Scanner<'a> {
         if self.is_at_end() {
             return false;
         }
-        if self.source.chars().nth(self.current) != Some(expected) {
+        if self.peek() != expected {
             return false;
         }
-        self.current += 1;
+        self.current += expected.len_utf8();
         true
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0'
-        }
-        return self.source.chars().nth(self.current).unwrap()
+        self.source.get(self.current..).and_then(|rest| rest.chars().next()).unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0'
-        }
-        return self.source.chars().nth(self.current + 1).unwrap()
+        self.source.get(self.current..).and_then(|rest| rest.chars().nth(1)).unwrap_or('\0')
     }
 
     fn comment(&mut self) -> Result<(), LoxError> {
-        Ok(while self.peek() != '\n' && !self.is_at_end() {
+        while self.peek() != '\n' && !self.is_at_end() {
             self.advance();
-        })
+        }
+        Ok(())
     }
 
     fn string(&mut self) -> Result<(), LoxError> {
@@ -176,7 +189,10 @@ impl<'a> Scanner<'a> {
             return Err(LoxError::ParseError {
                 line: self.line as usize,
                 where_: "".to_string(),
-                message: "Unterminated string.".to_string(),
+                message: UNTERMINATED_STRING.to_string(),
+                span: Span::new(self.start, self.current),
+                expected: Vec::new(),
+                hint: None,
             })
         }
         // The closing ".
@@ -212,11 +228,11 @@ impl<'a> Scanner<'a> {
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alpha_numeric(&self, c: char) -> bool {
@@ -231,58 +247,82 @@ mod tests {
 
     #[test]
     fn scan_tokens() {
-        let source = "{,.-}\n(+*;)\n//Hello World!!\n\r \t/!====>>=<<=\"Hello\nWorld!!\"12.34or,hello_world;
+        let source = "{,.-}\n(+*;?:)\n//Hello World!!\n\r \t/!====>>=<<=\"Hello\nWorld!!\"12.34or,hello_world;
             and class else false for fun if nil or print return super this true var while
         ";
         let expected = vec![
-            Token::new(TokenType::LeftBrace, "{", Literal::Nil, 1),
-            Token::new(TokenType::Comma, ",", Literal::Nil, 1),
-            Token::new(TokenType::Dot, ".", Literal::Nil, 1),
-            Token::new(TokenType::Minus, "-", Literal::Nil, 1),
-            Token::new(TokenType::RightBrace, "}", Literal::Nil, 1),
-            Token::new(TokenType::LeftParen, "(", Literal::Nil, 2),
-            Token::new(TokenType::Plus, "+", Literal::Nil, 2),
-            Token::new(TokenType::Star, "*", Literal::Nil, 2),
-            Token::new(TokenType::Semicolon, ";", Literal::Nil, 2),
-            Token::new(TokenType::RightParen, ")", Literal::Nil, 2),
-            Token::new(TokenType::Slash, "/", Literal::Nil, 4),
-            Token::new(TokenType::BangEqual, "!=", Literal::Nil, 4),
-            Token::new(TokenType::EqualEqual, "==", Literal::Nil, 4),
-            Token::new(TokenType::Equal, "=", Literal::Nil, 4),
-            Token::new(TokenType::Greater, ">", Literal::Nil, 4),
-            Token::new(TokenType::GreaterEqual, ">=", Literal::Nil, 4),
-            Token::new(TokenType::Less, "<", Literal::Nil, 4),
-            Token::new(TokenType::LessEqual, "<=", Literal::Nil, 4),
-            Token::new(TokenType::String, "\"Hello\nWorld!!\"", Literal::String("Hello\nWorld!!"), 5),
-            Token::new(TokenType::Number, "12.34", Literal::Number(12.34), 5),
-            Token::new(TokenType::Or, "or", Literal::Nil, 5),
-            Token::new(TokenType::Comma, ",", Literal::Nil, 5),
-            Token::new(TokenType::Identifier, "hello_world", Literal::Nil, 5),
-            Token::new(TokenType::Semicolon, ";", Literal::Nil, 5),
+            Token::new(TokenType::LeftBrace, "{", Literal::Nil, 1).with_span(0, 1),
+            Token::new(TokenType::Comma, ",", Literal::Nil, 1).with_span(1, 2),
+            Token::new(TokenType::Dot, ".", Literal::Nil, 1).with_span(2, 3),
+            Token::new(TokenType::Minus, "-", Literal::Nil, 1).with_span(3, 4),
+            Token::new(TokenType::RightBrace, "}", Literal::Nil, 1).with_span(4, 5),
+            Token::new(TokenType::LeftParen, "(", Literal::Nil, 2).with_span(6, 7),
+            Token::new(TokenType::Plus, "+", Literal::Nil, 2).with_span(7, 8),
+            Token::new(TokenType::Star, "*", Literal::Nil, 2).with_span(8, 9),
+            Token::new(TokenType::Semicolon, ";", Literal::Nil, 2).with_span(9, 10),
+            Token::new(TokenType::Question, "?", Literal::Nil, 2).with_span(10, 11),
+            Token::new(TokenType::Colon, ":", Literal::Nil, 2).with_span(11, 12),
+            Token::new(TokenType::RightParen, ")", Literal::Nil, 2).with_span(12, 13),
+            Token::new(TokenType::Slash, "/", Literal::Nil, 4).with_span(33, 34),
+            Token::new(TokenType::BangEqual, "!=", Literal::Nil, 4).with_span(34, 36),
+            Token::new(TokenType::EqualEqual, "==", Literal::Nil, 4).with_span(36, 38),
+            Token::new(TokenType::Equal, "=", Literal::Nil, 4).with_span(38, 39),
+            Token::new(TokenType::Greater, ">", Literal::Nil, 4).with_span(39, 40),
+            Token::new(TokenType::GreaterEqual, ">=", Literal::Nil, 4).with_span(40, 42),
+            Token::new(TokenType::Less, "<", Literal::Nil, 4).with_span(42, 43),
+            Token::new(TokenType::LessEqual, "<=", Literal::Nil, 4).with_span(43, 45),
+            Token::new(TokenType::String, "\"Hello\nWorld!!\"", Literal::String("Hello\nWorld!!"), 5).with_span(45, 60),
+            Token::new(TokenType::Number, "12.34", Literal::Number(12.34), 5).with_span(60, 65),
+            Token::new(TokenType::Or, "or", Literal::Nil, 5).with_span(65, 67),
+            Token::new(TokenType::Comma, ",", Literal::Nil, 5).with_span(67, 68),
+            Token::new(TokenType::Identifier, "hello_world", Literal::Nil, 5).with_span(68, 79),
+            Token::new(TokenType::Semicolon, ";", Literal::Nil, 5).with_span(79, 80),
             // add, class, else, false, for, fun, if, nil, or, print, return, super, this, true, var, while
-            Token::new(TokenType::And, "and", Literal::Nil, 6),
-            Token::new(TokenType::Class, "class", Literal::Nil, 6),
-            Token::new(TokenType::Else, "else", Literal::Nil, 6),
-            Token::new(TokenType::False, "false", Literal::Nil, 6),
-            Token::new(TokenType::For, "for", Literal::Nil, 6),
-            Token::new(TokenType::Fun, "fun", Literal::Nil, 6),
-            Token::new(TokenType::If, "if", Literal::Nil, 6),
-            Token::new(TokenType::Nil, "nil", Literal::Nil, 6),
-            Token::new(TokenType::Or, "or", Literal::Nil, 6),
-            Token::new(TokenType::Print, "print", Literal::Nil, 6),
-            Token::new(TokenType::Return, "return", Literal::Nil, 6),
-            Token::new(TokenType::Super, "super", Literal::Nil, 6),
-            Token::new(TokenType::This, "this", Literal::Nil, 6),
-            Token::new(TokenType::True, "true", Literal::Nil, 6),
-            Token::new(TokenType::Var, "var", Literal::Nil, 6),
-            Token::new(TokenType::While, "while", Literal::Nil, 6),
+            Token::new(TokenType::And, "and", Literal::Nil, 6).with_span(93, 96),
+            Token::new(TokenType::Class, "class", Literal::Nil, 6).with_span(97, 102),
+            Token::new(TokenType::Else, "else", Literal::Nil, 6).with_span(103, 107),
+            Token::new(TokenType::False, "false", Literal::Nil, 6).with_span(108, 113),
+            Token::new(TokenType::For, "for", Literal::Nil, 6).with_span(114, 117),
+            Token::new(TokenType::Fun, "fun", Literal::Nil, 6).with_span(118, 121),
+            Token::new(TokenType::If, "if", Literal::Nil, 6).with_span(122, 124),
+            Token::new(TokenType::Nil, "nil", Literal::Nil, 6).with_span(125, 128),
+            Token::new(TokenType::Or, "or", Literal::Nil, 6).with_span(129, 131),
+            Token::new(TokenType::Print, "print", Literal::Nil, 6).with_span(132, 137),
+            Token::new(TokenType::Return, "return", Literal::Nil, 6).with_span(138, 144),
+            Token::new(TokenType::Super, "super", Literal::Nil, 6).with_span(145, 150),
+            Token::new(TokenType::This, "this", Literal::Nil, 6).with_span(151, 155),
+            Token::new(TokenType::True, "true", Literal::Nil, 6).with_span(156, 160),
+            Token::new(TokenType::Var, "var", Literal::Nil, 6).with_span(161, 164),
+            Token::new(TokenType::While, "while", Literal::Nil, 6).with_span(165, 170),
             // eof
-            Token::new(TokenType::EOF, "", Literal::Nil, 7),
+            Token::new(TokenType::EOF, "", Literal::Nil, 7).with_span(179, 179),
         ];
         let mut scanner = Scanner::new(source);
         assert_eq!(scanner.scan_tokens().unwrap(), expected);
     }
 
+    #[test]
+    fn skip_shebang_line() {
+        let mut scanner = Scanner::new("#!/usr/bin/env lox\nnil");
+        let expected = vec![
+            Token::new(TokenType::Nil, "nil", Literal::Nil, 2).with_span(19, 22),
+            Token::new(TokenType::EOF, "", Literal::Nil, 2).with_span(22, 22),
+        ];
+        assert_eq!(scanner.scan_tokens().unwrap(), expected);
+        assert!(Scanner::new("nil #!").scan_tokens().is_err());
+    }
+
+    #[test]
+    fn scan_non_ascii_string() {
+        let mut scanner = Scanner::new("\"héllo\" 1");
+        let expected = vec![
+            Token::new(TokenType::String, "\"héllo\"", Literal::String("héllo"), 1).with_span(0, 8),
+            Token::new(TokenType::Number, "1", Literal::Number(1.0), 1).with_span(9, 10),
+            Token::new(TokenType::EOF, "", Literal::Nil, 1).with_span(10, 10),
+        ];
+        assert_eq!(scanner.scan_tokens().unwrap(), expected);
+    }
+
     #[test]
     fn throw_unexpected_character() {
         let source = "；";
@@ -0,0 +1,2 @@
+// Generated at build time by tool/generate_ast, see build.rs.
+include!(concat!(env!("OUT_DIR"), "/stmt.rs"));
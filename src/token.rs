@@ -1,10 +1,13 @@
-use std::fmt::Display;
+use std::{fmt::Display, rc::Rc};
+
+use crate::native::Callable;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen, RightParen, LeftBrace, RightBrace,
     Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+    Question, Colon,
 
     // One or two character tokens.
     Bang, BangEqual,
@@ -22,13 +25,75 @@ pub enum TokenType {
     EOF
 }
 
-pub trait Object: std::fmt::Debug {
-    fn box_clone(&self) -> Box<dyn Object>;
+impl Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::LeftParen => "'('",
+            Self::RightParen => "')'",
+            Self::LeftBrace => "'{'",
+            Self::RightBrace => "'}'",
+            Self::Comma => "','",
+            Self::Dot => "'.'",
+            Self::Minus => "'-'",
+            Self::Plus => "'+'",
+            Self::Semicolon => "';'",
+            Self::Slash => "'/'",
+            Self::Star => "'*'",
+            Self::Question => "'?'",
+            Self::Colon => "':'",
+            Self::Bang => "'!'",
+            Self::BangEqual => "'!='",
+            Self::Equal => "'='",
+            Self::EqualEqual => "'=='",
+            Self::Greater => "'>'",
+            Self::GreaterEqual => "'>='",
+            Self::Less => "'<'",
+            Self::LessEqual => "'<='",
+            Self::Identifier => "identifier",
+            Self::String => "string",
+            Self::Number => "number",
+            Self::And => "'and'",
+            Self::Class => "'class'",
+            Self::Else => "'else'",
+            Self::False => "'false'",
+            Self::Fun => "'fun'",
+            Self::For => "'for'",
+            Self::If => "'if'",
+            Self::Nil => "'nil'",
+            Self::Or => "'or'",
+            Self::Print => "'print'",
+            Self::Return => "'return'",
+            Self::Super => "'super'",
+            Self::This => "'this'",
+            Self::True => "'true'",
+            Self::Var => "'var'",
+            Self::While => "'while'",
+            Self::EOF => "end of file",
+        };
+        write!(f, "{}", text)
+    }
 }
 
-impl Clone for Box<dyn Object> {
-    fn clone(&self) -> Box<dyn Object> {
-        self.box_clone()
+/// A runtime value with identity, such as a function or a class instance.
+/// `Display` gives its representation in Lox, e.g. `<fn name>` or
+/// `Foo instance`.
+pub trait Object: std::fmt::Debug + Display {
+    /// The name of the object's type, as shown in error messages.
+    fn type_name(&self) -> &'static str;
+
+    /// The object as something that can be called, if it is one.
+    fn as_callable(&self) -> Option<&dyn Callable> {
+        None
+    }
+}
+
+/// Formats a number the way Lox prints it: integers without a fractional
+/// part, and infinities as `Infinity` and `-Infinity`.
+pub fn format_number(number: f64) -> String {
+    if number.is_infinite() {
+        String::from(if number > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        number.to_string()
     }
 }
 
@@ -46,14 +111,137 @@ impl<'a> std::fmt::Display for Literal<'a> {
             Self::Nil => write!(f, "nil"),
             Self::String(literal) => write!(f, "{}", literal),
             Self::Boolean(literal) => write!(f, "{}", literal),
-            Self::Number(literal) => write!(f, "{}", literal),
+            Self::Number(literal) => write!(f, "{}", format_number(*literal)),
+        }
+    }
+}
+
+/// A value produced while running a program. Strings are owned so that a
+/// value can outlive the source it was read from; objects are shared, so
+/// copies of one are the same object.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Value>),
+    Object(Rc<dyn Object>),
+}
+
+impl Value {
+    /// `nil` and `false` are falsey, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Nil | Self::Boolean(false))
+    }
+
+    /// Equality as defined by `==`: values of different types are never
+    /// equal, lists are compared element by element and objects by identity.
+    pub fn is_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Boolean(left), Self::Boolean(right)) => left == right,
+            (Self::Number(left), Self::Number(right)) => left == right,
+            (Self::String(left), Self::String(right)) => left == right,
+            (Self::List(left), Self::List(right)) => {
+                left.len() == right.len() && left.iter().zip(right).all(|(left, right)| left.is_equal(right))
+            },
+            (Self::Object(left), Self::Object(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+
+    /// The name of the value's type, as shown in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Boolean(_) => "boolean",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::List(_) => "list",
+            Self::Object(object) => object.type_name(),
+        }
+    }
+}
+
+impl<'a> From<&Literal<'a>> for Value {
+    fn from(literal: &Literal<'a>) -> Self {
+        match literal {
+            Literal::Nil => Value::Nil,
+            Literal::Boolean(value) => Value::Boolean(*value),
+            Literal::Number(value) => Value::Number(*value),
+            Literal::String(value) => Value::String(value.to_string()),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nil => write!(f, "nil"),
+            Self::Boolean(value) => write!(f, "{}", value),
+            Self::Number(value) => write!(f, "{}", format_number(*value)),
+            Self::String(value) => write!(f, "{}", value),
+            Self::List(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            },
+            Self::Object(object) => write!(f, "{}", object),
+        }
+    }
+}
+
+/// A range of byte offsets into the source, `end` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// Equality that ignores where in the source something came from, so that
+/// trees parsed from differently formatted sources, or built by hand, can be
+/// compared by shape.
+pub trait StructuralEq {
+    fn structural_eq(&self, other: &Self) -> bool;
+}
+
+impl<T: StructuralEq + ?Sized> StructuralEq for Box<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.as_ref().structural_eq(other.as_ref())
+    }
+}
+
+impl<T: StructuralEq> StructuralEq for Option<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(left), Some(right)) => left.structural_eq(right),
+            (None, None) => true,
+            _ => false,
         }
     }
 }
 
-pub enum Value<'a> {
-    Literal(Literal<'a>),
-    Object(Box<dyn Object>),
+impl<T: StructuralEq> StructuralEq for Vec<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(left, right)| left.structural_eq(right))
+    }
+}
+
+impl<'a> StructuralEq for Literal<'a> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self == other
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,6 +250,7 @@ pub struct Token<'a> {
     pub lexeme: &'a str,
     pub literal: Literal<'a>,
     pub line: u32,
+    pub span: Span,
 }
 
 impl<'a> Token<'a> {
@@ -71,8 +260,19 @@ impl<'a> Token<'a> {
             lexeme,
             literal,
             line,
+            span: Span::default(),
         }
     }
+
+    pub fn with_span(self, start: usize, end: usize) -> Token<'a> {
+        Token { span: Span::new(start, end), ..self }
+    }
+}
+
+impl<'a> StructuralEq for Token<'a> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.lexeme == other.lexeme && self.literal == other.literal
+    }
 }
 
 impl<'a> Display for Token<'a> {
@@ -80,3 +280,57 @@ impl<'a> Display for Token<'a> {
         write!(f, "{:?} {} {:?}", self.token_type, self.lexeme, self.literal)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Instance;
+
+    impl Display for Instance {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Foo instance")
+        }
+    }
+
+    impl Object for Instance {
+        fn type_name(&self) -> &'static str {
+            "Foo"
+        }
+    }
+
+    #[test]
+    fn display_values() {
+        let object: Rc<dyn Object> = Rc::new(Instance);
+        let values = [
+            (Value::Nil, "nil"),
+            (Value::Boolean(true), "true"),
+            (Value::Number(3.0), "3"),
+            (Value::Number(-0.5), "-0.5"),
+            (Value::Number(1.0 / 0.0), "Infinity"),
+            (Value::String(String::from("a b")), "a b"),
+            (Value::List(vec![Value::Number(1.0), Value::Nil]), "[1, nil]"),
+            (Value::Object(object), "Foo instance"),
+        ];
+        for (value, expected) in values {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn compare_values() {
+        let object: Rc<dyn Object> = Rc::new(Instance);
+        assert!(!Value::Nil.is_truthy());
+        assert!(!Value::Boolean(false).is_truthy());
+        assert!(Value::Number(0.0).is_truthy());
+        assert!(Value::String(String::new()).is_truthy());
+        assert!(Value::Nil.is_equal(&Value::Nil));
+        assert!(!Value::Nil.is_equal(&Value::Boolean(false)));
+        assert!(!Value::Number(f64::NAN).is_equal(&Value::Number(f64::NAN)));
+        assert!(Value::Object(object.clone()).is_equal(&Value::Object(object.clone())));
+        assert!(!Value::Object(object.clone()).is_equal(&Value::Object(Rc::new(Instance))));
+        assert_eq!(Value::Object(object).type_name(), "Foo");
+        assert_eq!(Value::List(Vec::new()).type_name(), "list");
+    }
+}
@@ -0,0 +1,89 @@
+use super::ast_printer::ExprPrinter;
+use super::expr::{Visitor, Expr, Assign, Unary, Binary, Call, Conditional, Literal, Grouping, Variable};
+
+/// Prints one node per line with its children indented beneath it, which
+/// makes the shape of deeply nested expressions easy to follow.
+#[derive(Default)]
+pub struct TreePrinter {
+    depth: usize,
+}
+
+impl Visitor<String> for TreePrinter {
+    fn visit_unary_expr(&mut self, expr: &Unary) -> String {
+        self.node(&format!("Unary {}", expr.operator.lexeme), &[expr.right.as_ref()])
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Binary) -> String {
+        let exprs = [expr.left.as_ref(), expr.right.as_ref()];
+        self.node(&format!("Binary {}", expr.operator.lexeme), &exprs)
+    }
+
+    fn visit_assign_expr(&mut self, expr: &Assign) -> String {
+        self.node(&format!("Assign {}", expr.name.lexeme), &[expr.value.as_ref()])
+    }
+
+    fn visit_call_expr(&mut self, expr: &Call) -> String {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+        self.node("Call", &exprs)
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> String {
+        let exprs = [expr.condition.as_ref(), expr.then_branch.as_ref(), expr.else_branch.as_ref()];
+        self.node("Conditional", &exprs)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        self.node(&format!("Literal {}", expr.value), &[])
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Grouping) -> String {
+        self.node("Grouping", &[expr.expression.as_ref()])
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        self.node(&format!("Variable {}", expr.name.lexeme), &[])
+    }
+}
+
+impl ExprPrinter for TreePrinter {
+    fn print(&mut self, expr: &Expr) -> String {
+        expr.accept(self as &mut dyn Visitor<String>)
+    }
+}
+
+impl TreePrinter {
+    fn node(&mut self, label: &str, children: &[&Expr]) -> String {
+        let mut result = "  ".repeat(self.depth);
+        result.push_str(label);
+        self.depth += 1;
+        for child in children {
+            result.push('\n');
+            result.push_str(&self.print(child));
+        }
+        self.depth -= 1;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print_tree() {
+        let program = crate::parse("-123 * (45.67 + 1);").unwrap();
+        let result = match &program.statements[0] {
+            crate::stmt::Stmt::Expression(expression) => TreePrinter::default().print(&expression.expression),
+            _ => panic!("Invalid statement"),
+        };
+        assert_eq!(result, "\
+Binary *
+  Unary -
+    Literal 123
+  Grouping
+    Binary +
+      Literal 45.67
+      Literal 1");
+    }
+}
@@ -0,0 +1,72 @@
+//! `StructuralEq` implementations, requested by listing `StructuralEq` among
+//! a base type's derives. Nodes compare field by field through the
+//! `structural_eq` method, which lets leaf types such as tokens leave their
+//! source positions out of the comparison.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use super::{enum_has_lifetime, ident, struct_has_lifetime, schema::StructInfo};
+
+pub(crate) const STRUCTURAL_EQ: &str = "StructuralEq";
+
+fn impl_header(name: &str, has_lifetime: bool) -> TokenStream {
+    let identifier = Ident::new(name, Span::call_site());
+    if has_lifetime {
+        quote!{impl<'a> StructuralEq for #identifier<'a>}
+    } else {
+        quote!{impl StructuralEq for #identifier}
+    }
+}
+
+pub(crate) fn define_structural_eq(object_name: &str, variants: &[StructInfo]) -> TokenStream {
+    let object_identifier = Ident::new(object_name, Span::call_site());
+    let enum_header = impl_header(object_name, enum_has_lifetime(variants));
+    let arms: Vec<TokenStream> = variants.iter().map(|variant| {
+        let variant_identifier = Ident::new(variant.name, Span::call_site());
+        quote!{
+            (#object_identifier::#variant_identifier(left), #object_identifier::#variant_identifier(right)) => {
+                left.structural_eq(right)
+            }
+        }
+    }).collect();
+    let fallback = if variants.len() > 1 {
+        quote!{_ => false,}
+    } else {
+        quote!{}
+    };
+    let variant_impls: Vec<TokenStream> = variants.iter().map(|variant| {
+        let header = impl_header(variant.name, struct_has_lifetime(&variant.fields));
+        if variant.fields.is_empty() {
+            return quote!{
+                #header {
+                    fn structural_eq(&self, _other: &Self) -> bool {
+                        true
+                    }
+                }
+            };
+        }
+        let comparisons: Vec<TokenStream> = variant.fields.iter().map(|field| {
+            let field_name = ident(field.field_name);
+            quote!{self.#field_name.structural_eq(&other.#field_name)}
+        }).collect();
+        quote!{
+            #header {
+                fn structural_eq(&self, other: &Self) -> bool {
+                    #(#comparisons)&&*
+                }
+            }
+        }
+    }).collect();
+    quote!{
+        #enum_header {
+            fn structural_eq(&self, other: &Self) -> bool {
+                match (self, other) {
+                    #(#arms)*
+                    #fallback
+                }
+            }
+        }
+        #(#variant_impls)*
+    }
+}
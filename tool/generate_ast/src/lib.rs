@@ -0,0 +1,300 @@
+use syn::parse_file;
+use proc_macro2::{TokenStream, Ident, Span};
+use quote::quote;
+use prettyplease::unparse;
+use std::{fmt::Display, path::Path};
+
+mod eq;
+mod schema;
+mod walk;
+
+use schema::{FieldInfo, FieldKind, FileInfo, Import, StructInfo};
+pub use schema::SchemaError;
+
+// Node and field names become bindings such as `Stmt::If(if)`, so names that
+// are Rust keywords are emitted as raw identifiers.
+fn ident(name: &str) -> Ident {
+    if syn::parse_str::<Ident>(name).is_ok() {
+        Ident::new(name, Span::call_site())
+    } else {
+        Ident::new_raw(name, Span::call_site())
+    }
+}
+
+fn define_base_object(base_object_name: &str, variants: &[StructInfo]) -> TokenStream {
+    let struct_name = Ident::new(base_object_name, Span::call_site());
+    let variant_names: Vec<&str> = variants.iter().map(|variant| {
+        variant.name
+    }).collect();
+    let has_lifetime = enum_has_lifetime(variants);
+    let variant_methods: Vec<TokenStream> = variant_names.iter().map(|variant_name| {
+        let identifier = Ident::new(variant_name, Span::call_site());
+        let identifier_lowercase = ident(&variant_name.to_lowercase());
+        quote!{
+            #struct_name::#identifier(#identifier_lowercase) => #identifier_lowercase.accept(visitor)
+        }
+    }).collect();
+    let variants: Vec<TokenStream> = variants.iter().map(|variant| {
+        let identifier = Ident::new(variant.name, Span::call_site());
+        if struct_has_lifetime(&variant.fields) {
+            quote!{#identifier(#identifier<'a>)}
+        } else {
+            quote!{#identifier(#identifier)}
+        }
+    }).collect();
+    let variants = quote!(#(#variants,)*);
+    if has_lifetime {
+        quote!{
+            enum #struct_name<'a> {
+                #variants
+            }
+            impl<'a> #struct_name<'a> {
+                pub fn accept<R>(&self, visitor: &'a mut dyn Visitor<R>) -> R {
+                    match self {
+                        #(#variant_methods),*
+                    }
+                }
+            }
+        }
+    } else {
+        quote!{
+            enum #struct_name {
+                #variants
+            }
+            impl #struct_name {
+                pub fn accept<'a, R>(&self, visitor: &'a mut dyn Visitor<R>) -> R {
+                    match self {
+                        #(#variant_methods),*
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn field_has_lifetime(field: &FieldInfo) -> bool {
+    field.field_type.has_lifetime
+}
+
+fn struct_has_lifetime(fields: &[FieldInfo]) -> bool {
+    fields.iter().any(|field| {
+        field_has_lifetime(field)
+    })
+}
+
+fn enum_has_lifetime(variants: &[StructInfo]) -> bool {
+    variants.iter().any(|variant| {
+        struct_has_lifetime(&variant.fields)
+    })
+}
+
+fn base_type(field: &FieldInfo) -> TokenStream {
+    let identifier = Ident::new(field.field_type.name, Span::call_site());
+    if field_has_lifetime(field) {
+        quote!{#identifier<'a>}
+    } else {
+        quote!{#identifier}
+    }
+}
+
+// The type of the field as stored in the node. Fields of the node's own base
+// type are boxed, unless a `Vec` already provides the indirection.
+fn field_type(object_name: &str, field: &FieldInfo) -> TokenStream {
+    let identifier_expr = base_type(field);
+    let recursive = object_name == field.field_type.name;
+    match field.field_type.kind {
+        FieldKind::Plain if recursive => quote!{Box<#identifier_expr>},
+        FieldKind::Plain => identifier_expr,
+        FieldKind::Optional if recursive => quote!{Option<Box<#identifier_expr>>},
+        FieldKind::Optional => quote!{Option<#identifier_expr>},
+        FieldKind::List => quote!{Vec<#identifier_expr>},
+    }
+}
+
+// The type of the field as taken by the node's constructor.
+fn parameter_type(field: &FieldInfo) -> TokenStream {
+    let identifier_expr = base_type(field);
+    match field.field_type.kind {
+        FieldKind::Plain => identifier_expr,
+        FieldKind::Optional => quote!{Option<#identifier_expr>},
+        FieldKind::List => quote!{Vec<#identifier_expr>},
+    }
+}
+
+fn define_variant(object_name: &str, variant_name: &str, fields: &[FieldInfo], derives: &TokenStream) -> TokenStream {
+    let variant_identifier = Ident::new(variant_name, Span::call_site());
+    let field_exprs: Vec<TokenStream> = fields.iter().map(|field| {
+        let left = ident(field.field_name);
+        let right = field_type(object_name, field);
+        quote!(#left: #right)
+    }).collect();
+    let func_field_exprs: Vec<TokenStream> = fields.iter().map(|field| {
+        let left = ident(field.field_name);
+        let right = parameter_type(field);
+        quote!(#left: #right)
+    }).collect();
+    let struct_fields: Vec<TokenStream> = fields.iter().map(|field| {
+        let field_name = ident(field.field_name);
+        if object_name != field.field_type.name {
+            return quote!{#field_name};
+        }
+        match field.field_type.kind {
+            FieldKind::Plain => quote!{#field_name: Box::new(#field_name)},
+            FieldKind::Optional => quote!{#field_name: #field_name.map(Box::new)},
+            FieldKind::List => quote!{#field_name},
+        }
+    }).collect();
+    let method_identifier = Ident::new(&format!("visit_{}_{}", variant_name.to_lowercase(), object_name.to_lowercase()), Span::call_site());
+    if struct_has_lifetime(fields) {
+        quote!{
+            #derives
+            pub struct #variant_identifier<'a> {
+                #(pub #field_exprs),*
+            }
+            impl<'a> #variant_identifier<'a> {
+                pub fn new(#(#func_field_exprs,)*) -> Self {
+                    #variant_identifier { #(#struct_fields,)* }
+                }
+
+                pub fn accept<R>(&self, visitor: &'a mut dyn Visitor<R>) -> R {
+                    visitor.#method_identifier(self)
+                }
+            }
+        }
+    } else {
+        quote!{
+            #derives
+            pub struct #variant_identifier {
+                #(pub #field_exprs),*
+            }
+            impl #variant_identifier {
+                pub fn new(#(#func_field_exprs,)*) -> Self {
+                    #variant_identifier { #(#struct_fields,)* }
+                }
+
+                pub fn accept<'a, R>(&self, visitor: &'a mut dyn Visitor<R>) -> R {
+                    visitor.#method_identifier(self)
+                }
+            }
+        }
+    }
+}
+
+fn define_visitor_trait(object_name: &str, variant_names: &Vec<&str>) -> TokenStream {
+    let methods = variant_names.iter().map(|variant_name| {
+        let variant_identifier = Ident::new(variant_name, Span::call_site());
+        let variant_identifier_lowercase = ident(&variant_name.to_lowercase());
+        let method_name = Ident::new(&format!("visit_{}_{}", &variant_name.to_lowercase(), &object_name.to_lowercase()), Span::call_site());
+        quote!(fn #method_name(&mut self, #variant_identifier_lowercase: &#variant_identifier) -> T)
+    }).collect::<Vec<TokenStream>>();
+    quote!{
+        pub trait Visitor<T> {
+            #(#methods;)*
+        }
+    }
+}
+
+// `StructuralEq` is implemented by the generator rather than derived.
+fn define_derives(derives: &[&str]) -> TokenStream {
+    let derives: Vec<TokenStream> = derives.iter()
+        .filter(|derive| **derive != eq::STRUCTURAL_EQ)
+        .map(|derive| {
+            let segments = derive.split("::").map(|segment| Ident::new(segment, Span::call_site()));
+            quote!{#(#segments)::*}
+        })
+        .collect();
+    if derives.is_empty() {
+        quote!{}
+    } else {
+        quote!{#[derive(#(#derives),*)]}
+    }
+}
+
+fn define_use_statements(dependencies: &[Import]) -> TokenStream {
+    let dependencies: Vec<TokenStream> = dependencies.iter().map(|dependency| {
+        let tokens = dependency.path.split("::").map(|token_str| Ident::new(token_str, Span::call_site()));
+        match dependency.alias {
+            Some(alias) => {
+                let alias = Ident::new(alias, Span::call_site());
+                quote!(use #(#tokens)::* as #alias)
+            },
+            None => quote!(use #(#tokens)::*),
+        }
+    }).collect();
+    quote!(#(#dependencies;)*)
+}
+
+fn define_type(output_dir: &Path, file_info: FileInfo, node_types: &[&str]) -> Result<(), std::io::Error> {
+    let variants = file_info.variants;
+    let expr_names: Vec<&str> = variants.iter().map(|variant| {
+        variant.name
+    }).collect();
+    let derives = define_derives(&file_info.derives);
+    let expr_enum = define_base_object(file_info.base_object_name, &variants);
+    let expr_variants: Vec<TokenStream> = variants.iter().map(|variant| {
+        define_variant(file_info.base_object_name, variant.name, &variant.fields, &derives)
+    }).collect();
+    let structural_eq = if file_info.derives.contains(&eq::STRUCTURAL_EQ) {
+        eq::define_structural_eq(file_info.base_object_name, &variants)
+    } else {
+        quote!{}
+    };
+    let visitor_trait = define_visitor_trait(file_info.base_object_name, &expr_names);
+    let visitor_mut_trait = walk::define_visitor_mut(file_info.base_object_name, &variants, node_types);
+    let fold_trait = walk::define_fold(file_info.base_object_name, &variants, node_types);
+    let use_statements = define_use_statements(&file_info.dependencies);
+    let tokens = quote!{
+        #use_statements
+        #derives
+        pub #expr_enum
+        #visitor_trait
+        #visitor_mut_trait
+        #fold_trait
+        #(#expr_variants)*
+        #structural_eq
+    };
+    let syntax_tree = parse_file(&tokens.to_string()).unwrap();
+    let result = unparse(&syntax_tree);
+    let file_path = output_dir.join(file_info.base_object_name.to_lowercase() + ".rs");
+    std::fs::write(file_path, result)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Schema(SchemaError),
+    Io(std::io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Schema(err) => write!(f, "{}", err),
+            Self::Io(err) => write!(f, "IO error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<SchemaError> for Error {
+    fn from(err: SchemaError) -> Self {
+        Self::Schema(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Parses `schema` and writes one module per base object (`expr.rs`,
+/// `stmt.rs`, ...) into `output_dir`. Called from the build script of `rlox`.
+pub fn generate(schema: &str, output_dir: &Path) -> Result<(), Error> {
+    let file_info_list = schema::parse_schema(schema)?;
+    let node_types: Vec<&str> = file_info_list.iter().map(|file_info| file_info.base_object_name).collect();
+    for file_info in file_info_list {
+        define_type(output_dir, file_info, &node_types)?;
+    }
+    Ok(())
+}
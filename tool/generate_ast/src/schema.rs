@@ -0,0 +1,406 @@
+use std::{collections::HashSet, fmt::Display};
+
+/// A malformed line in the AST schema.
+#[derive(Debug, PartialEq)]
+pub struct SchemaError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    /// `Type name`
+    Plain,
+    /// `Type? name`
+    Optional,
+    /// `Vec<Type> name`
+    List,
+}
+
+#[derive(Debug)]
+pub struct FieldType<'a> {
+    pub name: &'a str,
+    pub kind: FieldKind,
+    /// Whether the type takes the `'a` lifetime, see [`infer_lifetimes`].
+    pub has_lifetime: bool,
+}
+
+#[derive(Debug)]
+pub struct FieldInfo<'a> {
+    pub field_name: &'a str,
+    pub field_type: FieldType<'a>,
+}
+
+#[derive(Debug)]
+pub struct StructInfo<'a> {
+    pub name: &'a str,
+    pub fields: Vec<FieldInfo<'a>>
+}
+
+/// `use <path>[<'a>] [as <alias>]`. A `<'a>` suffix declares that the
+/// imported type borrows from the source and so takes a lifetime.
+#[derive(Debug, PartialEq)]
+pub struct Import<'a> {
+    pub path: &'a str,
+    pub alias: Option<&'a str>,
+    pub has_lifetime: bool,
+    pub line: usize,
+}
+
+impl<'a> Import<'a> {
+    /// The name the import is known by in the generated module.
+    pub fn local_name(&self) -> &'a str {
+        match self.alias {
+            Some(alias) => alias,
+            None => self.path.rsplit("::").next().unwrap_or(self.path),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FileInfo<'a> {
+    pub dependencies: Vec<Import<'a>>,
+    pub derives: Vec<&'a str>,
+    pub base_object_name: &'a str,
+    pub variants: Vec<StructInfo<'a>>,
+}
+
+fn error(line: usize, message: String) -> SchemaError {
+    SchemaError { line, message }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+fn is_path(s: &str) -> bool {
+    s.split("::").all(is_identifier)
+}
+
+fn parse_field_type(line: usize, s: &str) -> Result<FieldType<'_>, SchemaError> {
+    let (name, kind) = if let Some(inner) = s.strip_prefix("Vec<").and_then(|s| s.strip_suffix('>')) {
+        (inner, FieldKind::List)
+    } else if let Some(inner) = s.strip_suffix('?') {
+        (inner, FieldKind::Optional)
+    } else {
+        (s, FieldKind::Plain)
+    };
+    if !is_identifier(name) {
+        return Err(error(line, format!("Invalid field type '{}'.", s)));
+    }
+    Ok(FieldType { name, kind, has_lifetime: false })
+}
+
+fn parse_import(line: usize, s: &str) -> Result<Import<'_>, SchemaError> {
+    let s = s.trim().trim_end_matches(';');
+    let (path, alias) = match s.split_once(" as ") {
+        Some((path, alias)) => (path.trim(), Some(alias.trim())),
+        None => (s, None),
+    };
+    let (path, has_lifetime) = match path.strip_suffix("<'a>") {
+        Some(path) => (path, true),
+        None => (path, false),
+    };
+    if !is_path(path) {
+        return Err(error(line, format!("Invalid use path '{}'.", s)));
+    }
+    if let Some(alias) = alias {
+        if !is_identifier(alias) {
+            return Err(error(line, format!("Invalid alias '{}'.", alias)));
+        }
+    }
+    Ok(Import { path, alias, has_lifetime, line })
+}
+
+// A type takes a lifetime if it is imported with one, or if it is a node or
+// base type with a field whose type takes one. Node types can refer to each
+// other across base types, so this runs until nothing changes.
+fn infer_lifetimes(file_info_list: &mut [FileInfo]) {
+    let mut has_lifetime: HashSet<&str> = HashSet::new();
+    for file_info in file_info_list.iter() {
+        for import in &file_info.dependencies {
+            if import.has_lifetime {
+                has_lifetime.insert(import.local_name());
+            }
+        }
+    }
+    loop {
+        let mut changed = false;
+        for file_info in file_info_list.iter() {
+            for variant in &file_info.variants {
+                if variant.fields.iter().any(|field| has_lifetime.contains(field.field_type.name)) {
+                    changed |= has_lifetime.insert(variant.name);
+                    changed |= has_lifetime.insert(file_info.base_object_name);
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    for file_info in file_info_list.iter_mut() {
+        for variant in file_info.variants.iter_mut() {
+            for field in variant.fields.iter_mut() {
+                field.field_type.has_lifetime = has_lifetime.contains(field.field_type.name);
+            }
+        }
+    }
+}
+
+// An import whose name is also a node of the same base type would make the
+// generated module ambiguous.
+fn check_imports(file_info: &FileInfo) -> Result<(), SchemaError> {
+    for import in &file_info.dependencies {
+        let name = import.local_name();
+        if file_info.variants.iter().any(|variant| variant.name == name) || file_info.base_object_name == name {
+            return Err(error(import.line, format!(
+                "Import '{}' clashes with node '{}', rename it with 'use {} as <name>'.",
+                import.path, name, import.path,
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn parse_struct_info(line: usize, definition: &str) -> Result<StructInfo<'_>, SchemaError> {
+    let (struct_name, field_info_str) = match definition.split_once(':') {
+        Some(parts) => parts,
+        None => return Err(error(line, format!("Expect '<Name> : <Type> <field>, ...', found '{}'.", definition))),
+    };
+    let struct_name = struct_name.trim();
+    if !is_identifier(struct_name) {
+        return Err(error(line, format!("Invalid node name '{}'.", struct_name)));
+    }
+    let mut field_info_list: Vec<FieldInfo> = Vec::new();
+    if field_info_str.trim().is_empty() {
+        return Ok(StructInfo { name: struct_name, fields: field_info_list });
+    }
+    for field in field_info_str.split(',') {
+        let (field_type, field_name) = match field.split_whitespace().collect::<Vec<&str>>()[..] {
+            [field_type, field_name] => (field_type, field_name),
+            _ => return Err(error(line, format!("Expect '<Type> <field>', found '{}'.", field.trim()))),
+        };
+        if !is_identifier(field_name) {
+            return Err(error(line, format!("Invalid field name '{}'.", field_name)));
+        }
+        if field_info_list.iter().any(|field| field.field_name == field_name) {
+            return Err(error(line, format!("Duplicate field '{}' in '{}'.", field_name, struct_name)));
+        }
+        field_info_list.push(FieldInfo {
+            field_name,
+            field_type: parse_field_type(line, field_type)?,
+        });
+    }
+    Ok(StructInfo { name: struct_name, fields: field_info_list })
+}
+
+/// Parses the AST schema into one [`FileInfo`] per base type.
+///
+/// Each non-empty line outside of `#` comments is one of:
+///
+/// ```text
+/// base Expr
+/// use crate::token::Token<'a>
+/// use crate::token::Literal<'a> as LoxLiteral
+/// derive Debug, Clone
+/// Binary : Expr left, Token operator, Expr right
+/// ```
+pub fn parse_schema(source: &str) -> Result<Vec<FileInfo<'_>>, SchemaError> {
+    let mut file_info_list: Vec<FileInfo> = Vec::new();
+    let mut base_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let line = match line.split_once('#') {
+            Some((line, _)) => line,
+            None => line,
+        }.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("base ") {
+            if let Some(file_info) = file_info_list.last() {
+                if file_info.variants.is_empty() {
+                    return Err(error(base_line, format!("Base type '{}' has no variants.", file_info.base_object_name)));
+                }
+            }
+            let name = name.trim();
+            if !is_identifier(name) {
+                return Err(error(line_number, format!("Invalid base type name '{}'.", name)));
+            }
+            if file_info_list.iter().any(|file_info| file_info.base_object_name == name) {
+                return Err(error(line_number, format!("Duplicate base type '{}'.", name)));
+            }
+            base_line = line_number;
+            file_info_list.push(FileInfo {
+                dependencies: Vec::new(),
+                derives: Vec::new(),
+                base_object_name: name,
+                variants: Vec::new(),
+            });
+            continue;
+        }
+        let file_info = match file_info_list.last_mut() {
+            Some(file_info) => file_info,
+            None => return Err(error(line_number, String::from("Expect 'base <Name>' before any other definition."))),
+        };
+        if let Some(import) = line.strip_prefix("use ") {
+            file_info.dependencies.push(parse_import(line_number, import)?);
+            continue;
+        }
+        if let Some(derives) = line.strip_prefix("derive ") {
+            for derive in derives.split(',').map(str::trim) {
+                if !is_path(derive) {
+                    return Err(error(line_number, format!("Invalid derive '{}'.", derive)));
+                }
+                file_info.derives.push(derive);
+            }
+            continue;
+        }
+        let struct_info = parse_struct_info(line_number, line)?;
+        if file_info.variants.iter().any(|variant| variant.name == struct_info.name) {
+            return Err(error(line_number, format!("Duplicate node '{}'.", struct_info.name)));
+        }
+        file_info.variants.push(struct_info);
+    }
+    match file_info_list.last() {
+        None => Err(error(0, String::from("The schema defines no base types."))),
+        Some(file_info) if file_info.variants.is_empty() => {
+            Err(error(base_line, format!("Base type '{}' has no variants.", file_info.base_object_name)))
+        },
+        Some(_) => {
+            for file_info in &file_info_list {
+                check_imports(file_info)?;
+            }
+            infer_lifetimes(&mut file_info_list);
+            Ok(file_info_list)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Variant<'a> = (&'a str, Vec<(&'a str, &'a str, FieldKind)>);
+
+    #[test]
+    fn parse_fields() {
+        let schema = "
+            # comment
+            base Stmt
+            use crate::expr::Expr
+            derive Debug, Clone
+
+            Block : Vec<Stmt> statements
+            If    : Expr condition, Stmt then_branch, Stmt? else_branch # trailing comment
+            Empty :
+        ";
+        let file_info_list = parse_schema(schema).unwrap();
+        assert_eq!(file_info_list.len(), 1);
+        let file_info = &file_info_list[0];
+        assert_eq!(file_info.base_object_name, "Stmt");
+        assert_eq!(file_info.dependencies, vec![Import {
+            path: "crate::expr::Expr",
+            alias: None,
+            has_lifetime: false,
+            line: 4,
+        }]);
+        assert_eq!(file_info.derives, vec!["Debug", "Clone"]);
+        let variants: Vec<Variant> = file_info.variants.iter().map(|variant| {
+            let fields = variant.fields.iter().map(|field| {
+                (field.field_name, field.field_type.name, field.field_type.kind)
+            }).collect();
+            (variant.name, fields)
+        }).collect();
+        assert_eq!(variants, vec![
+            ("Block", vec![("statements", "Stmt", FieldKind::List)]),
+            ("If", vec![
+                ("condition", "Expr", FieldKind::Plain),
+                ("then_branch", "Stmt", FieldKind::Plain),
+                ("else_branch", "Stmt", FieldKind::Optional),
+            ]),
+            ("Empty", vec![]),
+        ]);
+    }
+
+    fn parse_error(schema: &str) -> SchemaError {
+        parse_schema(schema).unwrap_err()
+    }
+
+    #[test]
+    fn report_malformed_lines() {
+        assert_eq!(parse_error("Binary : Expr left"), SchemaError {
+            line: 1,
+            message: String::from("Expect 'base <Name>' before any other definition."),
+        });
+        assert_eq!(parse_error("base Expr\nBinary Expr left").to_string(),
+            "line 2: Expect '<Name> : <Type> <field>, ...', found 'Binary Expr left'.");
+        assert_eq!(parse_error("base Expr\n\nBinary : Expr left,, Expr right").to_string(),
+            "line 3: Expect '<Type> <field>', found ''.");
+        assert_eq!(parse_error("base Expr\nUnary : Vec<Expr right").to_string(),
+            "line 2: Invalid field type 'Vec<Expr'.");
+        assert_eq!(parse_error("base Expr\nUnary : Expr right, Token right").to_string(),
+            "line 2: Duplicate field 'right' in 'Unary'.");
+        assert_eq!(parse_error("base Expr\nderive Debug,\nUnary : Expr right").to_string(),
+            "line 2: Invalid derive ''.");
+        assert_eq!(parse_error("base Expr\nbase Stmt\nPrint : Expr expression").to_string(),
+            "line 1: Base type 'Expr' has no variants.");
+    }
+
+    #[test]
+    fn infer_lifetimes_across_base_types() {
+        let schema = "
+            base Expr
+            use crate::token::Token<'a>
+            use crate::token::Literal<'a> as LoxLiteral
+            Literal  : LoxLiteral value
+            Variable : Token name
+            Number   : f64 value
+            Negate   : Expr operand
+
+            base Stmt
+            use crate::expr::Expr
+            Block : Vec<Stmt> statements
+            Print : Expr expression
+
+            base Pattern
+            Wildcard : bool named
+        ";
+        let file_info_list = parse_schema(schema).unwrap();
+        let lifetimes: Vec<(&str, &str, bool)> = file_info_list.iter().flat_map(|file_info| {
+            file_info.variants.iter().flat_map(|variant| {
+                variant.fields.iter().map(|field| (variant.name, field.field_name, field.field_type.has_lifetime))
+            })
+        }).collect();
+        assert_eq!(lifetimes, vec![
+            ("Literal", "value", true),
+            ("Variable", "name", true),
+            ("Number", "value", false),
+            ("Negate", "operand", true),
+            ("Block", "statements", true),
+            ("Print", "expression", true),
+            ("Wildcard", "named", false),
+        ]);
+        assert_eq!(file_info_list[0].dependencies[1].local_name(), "LoxLiteral");
+    }
+
+    #[test]
+    fn report_clashing_import() {
+        let schema = "base Expr\nuse crate::token::Literal<'a>\nLiteral : Literal value";
+        assert_eq!(parse_error(schema).to_string(),
+            "line 2: Import 'crate::token::Literal' clashes with node 'Literal', \
+            rename it with 'use crate::token::Literal as <name>'.");
+    }
+}
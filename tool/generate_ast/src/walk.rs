@@ -0,0 +1,226 @@
+//! Traversal helpers emitted next to the `Visitor<T>` trait: a `VisitorMut`
+//! that rewrites nodes in place and a `Fold` that consumes and rebuilds them.
+//! Every trait method has a default that delegates to a generated `walk_*`
+//! function recursing into the node's children, so a pass only overrides the
+//! nodes it cares about.
+//!
+//! Fields of the node's own base type are walked. Fields of another base type
+//! (an `Expr` inside a `Stmt`) are handed to a hook method such as
+//! `visit_expr_mut` which does nothing by default, since that type's traversal
+//! lives in its own module.
+
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+use super::{base_type, enum_has_lifetime, ident, schema::{FieldInfo, FieldKind, StructInfo}};
+
+fn lowercase_ident(name: &str) -> Ident {
+    ident(&name.to_lowercase())
+}
+
+fn node_type(name: &str, has_lifetime: bool) -> TokenStream {
+    let identifier = ident(name);
+    if has_lifetime {
+        quote!{#identifier<'a>}
+    } else {
+        quote!{#identifier}
+    }
+}
+
+// Fields pointing at a node type, either the base type itself or another one
+// listed in `node_types`, in declaration order.
+fn node_fields<'b, 'a>(fields: &'b [FieldInfo<'a>], node_types: &[&str]) -> Vec<&'b FieldInfo<'a>> {
+    fields.iter().filter(|field| node_types.contains(&field.field_type.name)).collect()
+}
+
+// The node types, other than `object_name`, referenced by any variant.
+fn foreign_fields<'b, 'a>(object_name: &str, variants: &'b [StructInfo<'a>], node_types: &[&str]) -> Vec<&'b FieldInfo<'a>> {
+    let mut foreign: Vec<&FieldInfo> = Vec::new();
+    for variant in variants {
+        for field in node_fields(&variant.fields, node_types) {
+            let name = field.field_type.name;
+            if name != object_name && !foreign.iter().any(|other| other.field_type.name == name) {
+                foreign.push(field);
+            }
+        }
+    }
+    foreign
+}
+
+fn visit_mut_method(name: &str) -> Ident {
+    ident(&format!("visit_{}_mut", name.to_lowercase()))
+}
+
+fn fold_method(name: &str) -> Ident {
+    ident(&format!("fold_{}", name.to_lowercase()))
+}
+
+pub(crate) fn define_visitor_mut(object_name: &str, variants: &[StructInfo], node_types: &[&str]) -> TokenStream {
+    let object_identifier = ident(object_name);
+    let base = node_type(object_name, enum_has_lifetime(variants));
+    let base_param = lowercase_ident(object_name);
+    let base_method = visit_mut_method(object_name);
+    let base_walk = ident(&format!("walk_{}_mut", object_name.to_lowercase()));
+    let mut methods: Vec<TokenStream> = vec![quote!{
+        fn #base_method(&mut self, #base_param: &mut #base) {
+            #base_walk(self, #base_param)
+        }
+    }];
+    let mut walks: Vec<TokenStream> = Vec::new();
+    let arms: Vec<TokenStream> = variants.iter().map(|variant| {
+        let variant_identifier = ident(variant.name);
+        let param = lowercase_ident(variant.name);
+        let method = ident(&format!("visit_{}_{}_mut", variant.name.to_lowercase(), object_name.to_lowercase()));
+        quote!{#object_identifier::#variant_identifier(#param) => visitor.#method(#param)}
+    }).collect();
+    walks.push(quote!{
+        pub fn #base_walk<'a, V: VisitorMut<'a> + ?Sized>(visitor: &mut V, #base_param: &mut #base) {
+            match #base_param {
+                #(#arms,)*
+            }
+        }
+    });
+    for variant in variants {
+        let variant_type = node_type(variant.name, super::struct_has_lifetime(&variant.fields));
+        let param = lowercase_ident(variant.name);
+        let method = ident(&format!("visit_{}_{}_mut", variant.name.to_lowercase(), object_name.to_lowercase()));
+        let walk = ident(&format!("walk_{}_{}_mut", variant.name.to_lowercase(), object_name.to_lowercase()));
+        methods.push(quote!{
+            fn #method(&mut self, #param: &mut #variant_type) {
+                #walk(self, #param)
+            }
+        });
+        let fields = node_fields(&variant.fields, node_types);
+        let statements: Vec<TokenStream> = fields.iter().map(|field| {
+            let field_name = ident(field.field_name);
+            let method = visit_mut_method(field.field_type.name);
+            match field.field_type.kind {
+                FieldKind::Plain => quote!{visitor.#method(&mut #param.#field_name);},
+                FieldKind::Optional => quote!{
+                    if let Some(#field_name) = &mut #param.#field_name {
+                        visitor.#method(#field_name);
+                    }
+                },
+                FieldKind::List => quote!{
+                    for #field_name in #param.#field_name.iter_mut() {
+                        visitor.#method(#field_name);
+                    }
+                },
+            }
+        }).collect();
+        let (visitor_param, node_param) = if fields.is_empty() {
+            (ident("_visitor"), ident(&format!("_{}", variant.name.to_lowercase())))
+        } else {
+            (ident("visitor"), param)
+        };
+        walks.push(quote!{
+            pub fn #walk<'a, V: VisitorMut<'a> + ?Sized>(#visitor_param: &mut V, #node_param: &mut #variant_type) {
+                #(#statements)*
+            }
+        });
+    }
+    for field in foreign_fields(object_name, variants, node_types) {
+        let method = visit_mut_method(field.field_type.name);
+        let param = ident(&format!("_{}", field.field_type.name.to_lowercase()));
+        let field_type = base_type(field);
+        methods.push(quote!{
+            fn #method(&mut self, #param: &mut #field_type) {}
+        });
+    }
+    quote!{
+        pub trait VisitorMut<'a> {
+            #(#methods)*
+        }
+        #(#walks)*
+    }
+}
+
+pub(crate) fn define_fold(object_name: &str, variants: &[StructInfo], node_types: &[&str]) -> TokenStream {
+    let object_identifier = ident(object_name);
+    let base = node_type(object_name, enum_has_lifetime(variants));
+    let base_param = lowercase_ident(object_name);
+    let base_method = fold_method(object_name);
+    let base_walk = ident(&format!("walk_{}_fold", object_name.to_lowercase()));
+    let mut methods: Vec<TokenStream> = vec![quote!{
+        fn #base_method(&mut self, #base_param: #base) -> #base {
+            #base_walk(self, #base_param)
+        }
+    }];
+    let mut walks: Vec<TokenStream> = Vec::new();
+    let arms: Vec<TokenStream> = variants.iter().map(|variant| {
+        let variant_identifier = ident(variant.name);
+        let param = lowercase_ident(variant.name);
+        let method = ident(&format!("fold_{}_{}", variant.name.to_lowercase(), object_name.to_lowercase()));
+        quote!{#object_identifier::#variant_identifier(#param) => folder.#method(#param)}
+    }).collect();
+    walks.push(quote!{
+        pub fn #base_walk<'a, F: Fold<'a> + ?Sized>(folder: &mut F, #base_param: #base) -> #base {
+            match #base_param {
+                #(#arms,)*
+            }
+        }
+    });
+    for variant in variants {
+        let variant_identifier = ident(variant.name);
+        let variant_type = node_type(variant.name, super::struct_has_lifetime(&variant.fields));
+        let param = lowercase_ident(variant.name);
+        let method = ident(&format!("fold_{}_{}", variant.name.to_lowercase(), object_name.to_lowercase()));
+        let walk = ident(&format!("walk_{}_{}_fold", variant.name.to_lowercase(), object_name.to_lowercase()));
+        methods.push(quote!{
+            fn #method(&mut self, #param: #variant_type) -> #base {
+                #walk(self, #param)
+            }
+        });
+        let recursive = |field: &FieldInfo| field.field_type.name == object_name;
+        let initializers: Vec<TokenStream> = variant.fields.iter().map(|field| {
+            let field_name = ident(field.field_name);
+            if !node_types.contains(&field.field_type.name) {
+                return quote!{#field_name: #param.#field_name};
+            }
+            let method = fold_method(field.field_type.name);
+            match field.field_type.kind {
+                FieldKind::Plain if recursive(field) => quote!{
+                    #field_name: Box::new(folder.#method(*#param.#field_name))
+                },
+                FieldKind::Plain => quote!{#field_name: folder.#method(#param.#field_name)},
+                FieldKind::Optional if recursive(field) => quote!{
+                    #field_name: #param.#field_name.map(|#field_name| Box::new(folder.#method(*#field_name)))
+                },
+                FieldKind::Optional => quote!{
+                    #field_name: #param.#field_name.map(|#field_name| folder.#method(#field_name))
+                },
+                FieldKind::List => quote!{
+                    #field_name: #param.#field_name.into_iter().map(|#field_name| folder.#method(#field_name)).collect()
+                },
+            }
+        }).collect();
+        let folder_param = if node_fields(&variant.fields, node_types).is_empty() {
+            ident("_folder")
+        } else {
+            ident("folder")
+        };
+        walks.push(quote!{
+            pub fn #walk<'a, F: Fold<'a> + ?Sized>(#folder_param: &mut F, #param: #variant_type) -> #base {
+                #object_identifier::#variant_identifier(#variant_identifier {
+                    #(#initializers,)*
+                })
+            }
+        });
+    }
+    for field in foreign_fields(object_name, variants, node_types) {
+        let method = fold_method(field.field_type.name);
+        let param = lowercase_ident(field.field_type.name);
+        let field_type = base_type(field);
+        methods.push(quote!{
+            fn #method(&mut self, #param: #field_type) -> #field_type {
+                #param
+            }
+        });
+    }
+    quote!{
+        pub trait Fold<'a> {
+            #(#methods)*
+        }
+        #(#walks)*
+    }
+}